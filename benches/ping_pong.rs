@@ -0,0 +1,56 @@
+//! 两个线程之间用 One-Shot channel 来回传递一个计数器，测量每次往返的平均耗时.
+//! 每个 channel 只能用一次，所以每一轮都把下一轮要用的 Receiver 随消息一起发过去.
+//! 运行: cargo bench --bench ping_pong
+//! 对比伪共享的影响: cargo bench --bench ping_pong --no-default-features --features std
+//! 关闭 cache-padded，消息和状态字段落在同一条缓存行上.
+
+use std::mem;
+use std::thread;
+use std::time::Instant;
+
+use my_channel::{channel, Channel, OwnedReceiver, OwnedSender};
+
+const ROUNDS: u32 = 100_000;
+
+struct Ping {
+    n: u32,
+    reply: OwnedSender<u32>,
+    next: OwnedReceiver<Ping>,
+}
+
+fn main() {
+    let (mut ping, ping_rx) = channel::<Ping>();
+    let pong = thread::spawn(move || {
+        let mut rx = ping_rx;
+        while let Ok(Ping { n, reply, next }) = rx.recv() {
+            reply.send(n + 1).unwrap();
+            rx = next;
+        }
+    });
+
+    let start = Instant::now();
+    let mut n = 0;
+    while n < ROUNDS {
+        let (reply, reply_rx) = channel();
+        let (next, next_rx) = channel();
+        ping.send(Ping {
+            n,
+            reply,
+            next: next_rx,
+        })
+        .unwrap();
+        n = reply_rx.recv().unwrap();
+        ping = next;
+    }
+    let elapsed = start.elapsed();
+    drop(ping);
+    pong.join().unwrap();
+
+    println!(
+        "ping_pong: {} rounds in {:?} ({:?}/round), size_of::<Channel<Ping>>() = {}",
+        ROUNDS,
+        elapsed,
+        elapsed / ROUNDS,
+        mem::size_of::<Channel<Ping>>()
+    );
+}
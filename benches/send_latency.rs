@@ -0,0 +1,49 @@
+//! 测量 send 本身的耗时，对比接收方没有停放和已经停放两种情况.
+//! 接收方没有停放时，Waiter 的 parked 标志为 false，send 只付出一次 fence，不会去加锁和 unpark；
+//! 接收方已经停放时，send 还要加锁取出线程句柄并调用 unpark.
+//! 运行: cargo bench --bench send_latency
+
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use my_channel::channel;
+
+const ROUNDS: u32 = 100_000;
+const PARKED_ROUNDS: u32 = 200;
+
+fn main() {
+    // 同一个线程上先 send 再 try_recv: 接收方从未登记，send 跳过 unpark.
+    let mut total = Duration::ZERO;
+    for i in 0..ROUNDS {
+        let (sender, mut receiver) = channel();
+        let start = Instant::now();
+        sender.send(black_box(i)).unwrap();
+        total += start.elapsed();
+        black_box(receiver.try_recv().unwrap());
+    }
+    println!(
+        "send_latency/unparked: {:?}/send over {} sends",
+        total / ROUNDS,
+        ROUNDS
+    );
+
+    // 接收方在另一个线程上 recv 并停放，等它停放之后再计时 send.
+    let mut total = Duration::ZERO;
+    for i in 0..PARKED_ROUNDS {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || receiver.recv().unwrap());
+        while !format!("{sender:?}").contains("Thread") {
+            thread::yield_now();
+        }
+        let start = Instant::now();
+        sender.send(black_box(i)).unwrap();
+        total += start.elapsed();
+        assert_eq!(handle.join().unwrap(), i);
+    }
+    println!(
+        "send_latency/parked: {:?}/send over {} sends",
+        total / PARKED_ROUNDS,
+        PARKED_ROUNDS
+    );
+}
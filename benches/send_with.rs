@@ -0,0 +1,49 @@
+//! 对比 send 与 send_with 发送一个很大的数组: send 先在调用方的栈上构造消息再拷贝进通道，
+//! send_with 由 f 的返回值直接写入通道的存储，省去这次拷贝(release 下通常可以被优化掉).
+//! 每一轮复用同一个堆上的 Channel，只计时 send 本身，消息之后由 take 取回.
+//! 运行: cargo bench --bench send_with
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use my_channel::Channel;
+
+const SIZE: usize = 64 * 1024;
+const ROUNDS: u32 = 20_000;
+
+type Payload = [u8; SIZE];
+
+fn main() {
+    let mut channel: Box<Channel<Payload>> = Box::default();
+
+    let mut total = Duration::ZERO;
+    for i in 0..ROUNDS {
+        let (sender, receiver) = channel.split();
+        let byte = black_box(i as u8);
+        let start = Instant::now();
+        sender.send([byte; SIZE]).unwrap();
+        total += start.elapsed();
+        drop(receiver);
+        black_box(channel.take().unwrap()[0]);
+    }
+    report("send", total);
+
+    let mut total = Duration::ZERO;
+    for i in 0..ROUNDS {
+        let (sender, receiver) = channel.split();
+        let byte = black_box(i as u8);
+        let start = Instant::now();
+        sender.send_with(|| [byte; SIZE]).unwrap();
+        total += start.elapsed();
+        drop(receiver);
+        black_box(channel.take().unwrap()[0]);
+    }
+    report("send_with", total);
+}
+
+fn report(name: &str, total: Duration) {
+    println!(
+        "send_with/{name}: {:?}/send over {ROUNDS} sends of {SIZE} bytes",
+        total / ROUNDS
+    );
+}
@@ -0,0 +1,50 @@
+//! 两个线程之间通过 spsc::Channel 传递整数的吞吐量，与堆上的 bounded channel 对比.
+//! 生产者用阻塞的 send，消费者用阻塞的 recv，队列满/空时双方都会停放.
+//! 运行: cargo bench --bench spsc_throughput
+
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+use my_channel::{bounded, spsc};
+
+const MESSAGES: u64 = 2_000_000;
+const CAPACITY: usize = 1024;
+
+fn main() {
+    let mut channel = spsc::Channel::<u64, CAPACITY>::new();
+    let (mut sender, mut receiver) = channel.split();
+    let start = Instant::now();
+    thread::scope(|s| {
+        s.spawn(move || {
+            for i in 0..MESSAGES {
+                sender.send(black_box(i)).unwrap();
+            }
+        });
+        let mut sum = 0;
+        while let Ok(msg) = receiver.recv() {
+            sum += msg;
+        }
+        assert_eq!(sum, MESSAGES * (MESSAGES - 1) / 2);
+    });
+    report("spsc", start.elapsed());
+
+    let (mut sender, receiver) = bounded::channel::<u64>(CAPACITY);
+    let start = Instant::now();
+    let handle = thread::spawn(move || {
+        for i in 0..MESSAGES {
+            sender.send(black_box(i)).unwrap();
+        }
+    });
+    let sum: u64 = receiver.into_iter().sum();
+    handle.join().unwrap();
+    assert_eq!(sum, MESSAGES * (MESSAGES - 1) / 2);
+    report("bounded", start.elapsed());
+}
+
+fn report(name: &str, elapsed: std::time::Duration) {
+    println!(
+        "spsc_throughput/{name}: {:.1} M msgs/s ({MESSAGES} messages, capacity {CAPACITY})",
+        MESSAGES as f64 / elapsed.as_secs_f64() / 1e6
+    );
+}
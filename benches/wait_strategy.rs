@@ -0,0 +1,83 @@
+//! 与 ping_pong 相同地在两个线程之间来回传递计数器，对比不同等待策略下每次往返的耗时.
+//! 对方几乎立即回复，Spin 和 SpinThenPark 在自旋期间就能等到消息，省去 park/unpark；
+//! 在单核机器上自旋的线程会占住唯一的核，Spin 反而比 Park 慢得多.
+//! slow 一组让发送方先睡眠再回复，确认默认的 SpinThenPark 在长时间等待时相对 Park 没有退化.
+//! 运行: cargo bench --bench wait_strategy
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use my_channel::{Channel, OwnedReceiver, OwnedSender, Park, Spin, SpinThenPark, WaitStrategy};
+
+const ROUNDS: u32 = 10_000;
+const SLOW_ROUNDS: u32 = 200;
+const SLOW_DELAY: Duration = Duration::from_micros(200);
+
+struct Ping {
+    n: u32,
+    reply: OwnedSender<u32>,
+    next: OwnedReceiver<Ping>,
+}
+
+fn ping_pong(name: &str, strategy: &'static dyn WaitStrategy, rounds: u32, delay: Duration) {
+    let (mut ping, ping_rx) = Channel::<Ping>::new_shared_with(strategy);
+    let pong = thread::spawn(move || {
+        let mut rx = ping_rx;
+        while let Ok(Ping { n, reply, next }) = rx.recv() {
+            if !delay.is_zero() {
+                thread::sleep(delay);
+            }
+            reply.send(n + 1).unwrap();
+            rx = next;
+        }
+    });
+
+    let start = Instant::now();
+    let mut n = 0;
+    while n < rounds {
+        let (reply, reply_rx) = Channel::new_shared_with(strategy);
+        let (next, next_rx) = Channel::new_shared_with(strategy);
+        ping.send(Ping {
+            n,
+            reply,
+            next: next_rx,
+        })
+        .unwrap();
+        n = reply_rx.recv().unwrap();
+        ping = next;
+    }
+    let elapsed = start.elapsed();
+    drop(ping);
+    pong.join().unwrap();
+
+    println!(
+        "wait_strategy/{name}: {:?}/round over {} rounds",
+        elapsed / rounds,
+        rounds
+    );
+}
+
+fn main() {
+    let none = Duration::ZERO;
+    ping_pong("park", &Park, ROUNDS, none);
+    ping_pong("default", &SpinThenPark::DEFAULT, ROUNDS, none);
+    ping_pong("slow/park", &Park, SLOW_ROUNDS, SLOW_DELAY);
+    ping_pong(
+        "slow/default",
+        &SpinThenPark::DEFAULT,
+        SLOW_ROUNDS,
+        SLOW_DELAY,
+    );
+    if thread::available_parallelism().map_or(1, |n| n.get()) < 2 {
+        // 每次交接都要等自旋的线程用完时间片，一轮就要几毫秒，没有比较的意义.
+        println!("wait_strategy: single core, skipping spin strategies");
+        return;
+    }
+    ping_pong("spin", &Spin, ROUNDS, none);
+    ping_pong(
+        "spin_then_park",
+        &SpinThenPark { spins: 1000 },
+        ROUNDS,
+        none,
+    );
+}
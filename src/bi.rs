@@ -0,0 +1,149 @@
+//! 请求-应答式的双向通道: 由两个 One-Shot channel 组成，A 方向 B 方发送一个请求(A)，
+//! B 方向 A 方发送一个应答(B). 每个方向各传递一条消息，与 Channel 一样只用一次.
+//! 两个 Endpoint 各自持有一个方向的 Sender 和另一个方向的 Receiver，
+//! 调用方不需要手动创建并交叉传递两对 Sender/Receiver.
+
+#[cfg(feature = "std")]
+use crate::RecvError;
+use crate::{Channel, Receiver, SendError, Sender, TryRecvError};
+
+/// 创建一个放在堆上的双向通道，两端都是 'static 的，可以 move 进 thread::spawn 的线程.
+pub fn bi_channel<A, B>() -> (EndpointA<'static, A, B>, EndpointB<'static, A, B>) {
+    let (request_tx, request_rx) = Channel::new_shared();
+    let (reply_tx, reply_rx) = Channel::new_shared();
+    (
+        EndpointA {
+            tx: Some(request_tx),
+            rx: Some(reply_rx),
+        },
+        EndpointB {
+            tx: Some(reply_tx),
+            rx: Some(request_rx),
+        },
+    )
+}
+
+/// 放在栈上的双向通道，与 Channel 一样通过 split 借出两端.
+#[derive(Debug, Default)]
+pub struct BiChannel<A, B> {
+    request: Channel<A>,
+    reply: Channel<B>,
+}
+
+impl<A, B> BiChannel<A, B> {
+    pub fn new() -> Self {
+        Self {
+            request: Channel::new(),
+            reply: Channel::new(),
+        }
+    }
+
+    /// 与 Channel::split 相同，重置两个方向并交出两端.
+    pub fn split(&mut self) -> (EndpointA<'_, A, B>, EndpointB<'_, A, B>) {
+        let (request_tx, request_rx) = self.request.split();
+        let (reply_tx, reply_rx) = self.reply.split();
+        (
+            EndpointA {
+                tx: Some(request_tx),
+                rx: Some(reply_rx),
+            },
+            EndpointB {
+                tx: Some(reply_tx),
+                rx: Some(request_rx),
+            },
+        )
+    }
+}
+
+macro_rules! endpoint {
+    ($(#[$attr:meta])* $name:ident, $send:ident, $recv:ident) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<'a, A, B> {
+            // 发送或接收之后被取走.
+            tx: Option<Sender<'a, $send>>,
+            rx: Option<Receiver<'a, $recv>>,
+        }
+
+        impl<'a, A, B> $name<'a, A, B> {
+            /// 发送这一端的消息. 已经发送过，或者对方已经被丢弃时通过 SendError 交还消息.
+            pub fn send(&mut self, msg: $send) -> Result<(), SendError<$send>> {
+                match self.tx.take() {
+                    Some(tx) => tx.send(msg),
+                    None => Err(SendError(msg)),
+                }
+            }
+
+            /// 非阻塞地接收对方的消息，见 Receiver::try_recv. 已经接收过时返回 Disconnected.
+            pub fn try_recv(&mut self) -> Result<$recv, TryRecvError> {
+                let rx = self.rx.as_mut().ok_or(TryRecvError::Disconnected)?;
+                let msg = rx.try_recv()?;
+                self.rx = None;
+                Ok(msg)
+            }
+
+            /// 阻塞直到对方的消息到达，见 Receiver::recv. 已经接收过时返回 Disconnected.
+            #[cfg(feature = "std")]
+            pub fn recv(&mut self) -> Result<$recv, RecvError> {
+                self.rx.take().ok_or(RecvError::Disconnected)?.recv()
+            }
+
+            /// 拆成底层的 Sender 和 Receiver，已经用过的一半为 None.
+            pub fn into_parts(self) -> (Option<Sender<'a, $send>>, Option<Receiver<'a, $recv>>) {
+                (self.tx, self.rx)
+            }
+        }
+    };
+}
+
+endpoint! {
+    /// 发送请求 A、接收应答 B 的一端.
+    EndpointA, A, B
+}
+
+endpoint! {
+    /// 接收请求 A、发送应答 B 的一端.
+    EndpointB, B, A
+}
+
+#[cfg(all(test, feature = "std", not(loom)))]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn request_reply() {
+        let (mut client, mut server) = bi_channel::<u32, String>();
+        let handle = thread::spawn(move || {
+            let request = server.recv().unwrap();
+            server.send(format!("got {request}")).unwrap();
+        });
+        client.send(7).unwrap();
+        assert_eq!(client.recv().as_deref(), Ok("got 7"));
+        handle.join().unwrap();
+        // 每个方向只传递一条消息.
+        assert_eq!(client.send(8), Err(SendError(8)));
+        assert_eq!(client.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn scoped_and_disconnect() {
+        let mut channel = BiChannel::<i32, i32>::new();
+        thread::scope(|s| {
+            let (mut a, mut b) = channel.split();
+            s.spawn(move || {
+                let n = b.recv().unwrap();
+                b.send(n * 2).unwrap();
+            });
+            a.send(21).unwrap();
+            assert_eq!(a.recv(), Ok(42));
+        });
+
+        let (mut a, b) = channel.split();
+        assert_eq!(a.try_recv(), Err(TryRecvError::Empty));
+        // 对方没有应答就被丢弃.
+        drop(b);
+        assert_eq!(a.recv(), Err(RecvError::Disconnected));
+    }
+}
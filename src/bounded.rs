@@ -0,0 +1,1221 @@
+//! 有界的 SPSC(单生产者/单消费者) channel，基于环形缓冲区实现.
+//! 与 One-Shot channel 不同，它可以连续发送多条消息:
+//!     1.每个槽位是一个 UnsafeCell<MaybeUninit<T>>，用于存储message；
+//!     2.head/tail 两个 AtomicUsize 分别是下一个要读/写的位置. 它们只增不减(回绕相加)，
+//!       取模 capacity 得到槽位下标，tail - head 即队列中的消息数，不会与"空"混淆.
+//! 生产者只写 tail，消费者只写 head，因此不需要锁; 队列满/空时用 park/unpark 等待对方.
+//! Sender 被丢弃之后，Receiver 读完队列中剩下的消息就会收到 Disconnected，
+//! 因此可以直接用 for 循环接收，循环在 Sender 被丢弃且队列为空时结束.
+//! 只支持一个生产者和一个消费者: Sender/Receiver 都不能 clone，send/recv 都需要 &mut self.
+//! capacity 为 1 时，每条消息都像 One-Shot channel 一样经由唯一的槽位交给接收方，
+//! 区别只在于上一条消息被取走之前，下一次 send 会阻塞.
+//! 开启 futures feature 后，Sender::into_multi 把 Sender 换成可以 clone 的 MultiSender，
+//! 供多个异步生产者共用，它们在队列满时按先来后到排队，见 MultiSender.
+
+#[cfg(feature = "futures")]
+use std::collections::VecDeque;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "futures")]
+use std::task::Waker;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "futures")]
+use crate::sync::{fence, Mutex};
+use crate::sync::{Arc, AtomicBool, AtomicUsize, UnsafeCell};
+use crate::{ChannelRef, RecvError, SendError, SendTimeoutError, TryRecvError, Waiter};
+
+/// 创建一个放在堆上、最多缓存 capacity 条消息的 bounded channel，两端都是 'static 的.
+/// 与 BoundedChannel::split 共用同一套实现，只是不需要 thread::scope.
+///
+/// # Panics
+/// capacity 为 0 时 panic.
+pub fn channel<T>(capacity: usize) -> (Sender<'static, T>, Receiver<'static, T>) {
+    let channel = Arc::new(BoundedChannel::new(capacity));
+    (
+        Sender {
+            inner: ChannelRef::Shared(channel.clone()),
+        },
+        Receiver {
+            inner: ChannelRef::Shared(channel),
+        },
+    )
+}
+
+pub struct Sender<'a, T> {
+    inner: ChannelRef<'a, BoundedChannel<T>>,
+}
+
+pub struct Receiver<'a, T> {
+    inner: ChannelRef<'a, BoundedChannel<T>>,
+}
+
+impl<T> Sender<'_, T> {
+    /// 见 BoundedChannel::capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// 见 BoundedChannel::len.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 发送一条消息，队列已满时阻塞，直到消费者取走一条消息.
+    /// Receiver 已经被丢弃(包括在等待期间被丢弃)时不写入槽位，通过 SendError 交还消息.
+    pub fn send(&mut self, msg: T) -> Result<(), SendError<T>> {
+        let channel = &*self.inner;
+        if channel.send_would_block() {
+            channel
+                .send_waiter
+                .park_while(|| !channel.is_closed() && channel.send_would_block());
+        }
+        if channel.is_closed() {
+            return Err(SendError(msg));
+        }
+        channel.push(msg);
+        Ok(())
+    }
+
+    /// 与 send 相同，但最多等待 timeout: 队列已满时停放(park_timeout)，消费者取走消息时被唤醒.
+    /// 超时之前一直没有空闲槽位时返回 Timeout，Receiver 已经被丢弃时返回 Disconnected，
+    /// 两种情况都不会写入任何槽位，消息通过错误原样交还. 虚假唤醒之后按剩余时间继续等待.
+    pub fn send_timeout(&mut self, msg: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let channel = &*self.inner;
+        // 超时太长以至于无法表示时当作永远等待.
+        let deadline = Instant::now().checked_add(timeout);
+        if channel.send_would_block()
+            && !channel.send_waiter.park_while_until(
+                || !channel.is_closed() && channel.send_would_block(),
+                deadline,
+            )
+        {
+            return Err(SendTimeoutError::Timeout(msg));
+        }
+        if channel.is_closed() {
+            return Err(SendTimeoutError::Disconnected(msg));
+        }
+        channel.push(msg);
+        Ok(())
+    }
+}
+
+impl<T> Drop for Sender<'_, T> {
+    /// 唤醒等待中的 Receiver，让它读完剩下的消息之后返回 Disconnected 而不是永远停放.
+    fn drop(&mut self) {
+        // Release: 看到 disconnected 的 Receiver 也能看到之前 push 的所有消息.
+        self.inner.disconnected.store(true, Ordering::Release);
+        self.inner.recv_waiter.unpark();
+    }
+}
+
+/// 异步的 send/recv 只依赖 Waker，不会 park 线程，也不依赖任何特定的异步运行时.
+/// 它们与阻塞的 send/recv 共用 send_waiter/recv_waiter: Waiter 中登记的可以是线程也可以是 Waker，
+/// 所以一端阻塞、另一端异步也能互相唤醒.
+#[cfg(feature = "async")]
+impl<T> Sender<'_, T> {
+    /// 等待空闲槽位并发送. Receiver 已经被丢弃时通过 SendError 交还消息.
+    /// 等待期间丢弃这个 Future 不会占用任何槽位: 槽位只在 Future 完成的那一刻写入，
+    /// 消息随 Future 一起被丢弃，登记的 Waker 最多带来一次虚假唤醒.
+    pub async fn send_async(&mut self, msg: T) -> Result<(), SendError<T>> {
+        let channel = &*self.inner;
+        let open = core::future::poll_fn(|cx| {
+            let ready = || channel.is_closed() || !channel.send_would_block();
+            if ready() {
+                return Poll::Ready(!channel.is_closed());
+            }
+            channel.send_waiter.register_waker(cx.waker());
+            if !ready() {
+                return Poll::Pending;
+            }
+            channel.send_waiter.unregister();
+            Poll::Ready(!channel.is_closed())
+        })
+        .await;
+        if !open {
+            return Err(SendError(msg));
+        }
+        channel.push(msg);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Receiver<'_, T> {
+    /// 等待并取出一条消息. Sender 已经被丢弃并且队列为空时返回 None.
+    pub async fn recv_async(&mut self) -> Option<T> {
+        let channel = &*self.inner;
+        core::future::poll_fn(|cx| {
+            let ready = || !channel.recv_would_block() || channel.is_disconnected();
+            if ready() {
+                return Poll::Ready(());
+            }
+            channel.recv_waiter.register_waker(cx.waker());
+            if !ready() {
+                return Poll::Pending;
+            }
+            channel.recv_waiter.unregister();
+            Poll::Ready(())
+        })
+        .await;
+        // 与 recv 相同: 看到 disconnected 之后队列仍为空就不会再有消息.
+        if channel.recv_would_block() {
+            return None;
+        }
+        Some(self.pop())
+    }
+}
+
+impl<'a, T> Receiver<'a, T> {
+    /// 见 BoundedChannel::capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// 见 BoundedChannel::len.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 接收一条消息，队列为空时阻塞，直到生产者发送一条消息.
+    /// Sender 已经被丢弃并且队列为空时返回 Disconnected.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.wait()?;
+        Ok(self.pop())
+    }
+
+    /// 阻塞直到至少有一条消息，然后不再阻塞地取出队列中现有的消息，最多 max 条，追加到 buf 末尾，
+    /// 返回取出的条数. 整批消息只读一次 tail、推进一次 head、唤醒一次 Sender，
+    /// 比逐条 recv 少了每条消息各一次的原子操作和 unpark.
+    /// 返回 0 表示 Sender 已经被丢弃并且队列为空(或者 max 为 0).
+    pub fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        if max == 0 || self.wait().is_err() {
+            return 0;
+        }
+        let channel = &*self.inner;
+        let head = channel.head.load(Ordering::Relaxed);
+        let count = channel
+            .tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(head)
+            .min(max);
+        buf.reserve(count);
+        for i in 0..count {
+            let slot = channel.slot(head.wrapping_add(i));
+            buf.push(slot.with(|ptr| unsafe { (*ptr).assume_init_read() }));
+        }
+        // 与 pop 相同，Release 之后这些槽位才可以被生产者覆盖.
+        channel
+            .head
+            .store(head.wrapping_add(count), Ordering::Release);
+        channel.wake_sender();
+        count
+    }
+
+    /// 非阻塞地接收一条消息. 队列为空时，Sender 已经被丢弃返回 Disconnected，否则返回 Empty.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let channel = &*self.inner;
+        if channel.recv_would_block() {
+            if !channel.is_disconnected() {
+                return Err(TryRecvError::Empty);
+            }
+            if channel.recv_would_block() {
+                return Err(TryRecvError::Disconnected);
+            }
+        }
+        Ok(self.pop())
+    }
+
+    /// 阻塞地逐条接收，直到 Sender 被丢弃并且队列为空. 与 for msg in &mut receiver 相同.
+    pub fn iter(&mut self) -> Iter<'_, 'a, T> {
+        Iter { receiver: self }
+    }
+
+    /// 不阻塞地取出队列中现有的消息，队列为空时结束(之后到达的消息可以由下一次 try_iter 取得).
+    pub fn try_iter(&mut self) -> TryIter<'_, 'a, T> {
+        TryIter { receiver: self }
+    }
+
+    /// 队列为空时阻塞，直到有消息或 Sender 被丢弃.
+    fn wait(&self) -> Result<(), RecvError> {
+        let channel = &*self.inner;
+        if channel.recv_would_block() {
+            channel
+                .recv_waiter
+                .park_while(|| channel.recv_would_block() && !channel.is_disconnected());
+            // 看到 disconnected 之后，Sender 发送过的消息都已可见，队列仍为空就不会再有消息.
+            if channel.recv_would_block() {
+                return Err(RecvError::Disconnected);
+            }
+        }
+        Ok(())
+    }
+
+    /// 从 head 处的槽位取出消息. 调用方需要保证队列非空.
+    fn pop(&mut self) -> T {
+        let channel = &*self.inner;
+        let head = channel.head.load(Ordering::Relaxed);
+        let msg = channel
+            .slot(head)
+            .with(|ptr| unsafe { (*ptr).assume_init_read() });
+        // Release: 生产者用 Acquire 读到新的 head 时，这个槽位已经读完，可以被覆盖.
+        channel.head.store(head.wrapping_add(1), Ordering::Release);
+        channel.wake_sender();
+        msg
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    /// 让等待空闲槽位的 send / send_async 和所有排队中的 MultiSender 返回 SendError，
+    /// 而不是永远等下去.
+    fn drop(&mut self) {
+        self.inner.closed.store(true, Ordering::Release);
+        self.inner.send_waiter.unpark();
+        #[cfg(feature = "futures")]
+        self.inner.wake_all_queued();
+    }
+}
+
+/// Receiver::iter 返回的阻塞迭代器.
+pub struct Iter<'r, 'a, T> {
+    receiver: &'r mut Receiver<'a, T>,
+}
+
+impl<T> Iterator for Iter<'_, '_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Receiver::try_iter 返回的非阻塞迭代器.
+pub struct TryIter<'r, 'a, T> {
+    receiver: &'r mut Receiver<'a, T>,
+}
+
+impl<T> Iterator for TryIter<'_, '_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// 由 Receiver 的 into_iter 得到，拥有 Receiver，每次 next 都调用 recv.
+pub struct IntoIter<'a, T> {
+    receiver: Receiver<'a, T>,
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for Receiver<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> IntoIter<'a, T> {
+        IntoIter { receiver: self }
+    }
+}
+
+impl<'r, 'a, T> IntoIterator for &'r mut Receiver<'a, T> {
+    type Item = T;
+    type IntoIter = Iter<'r, 'a, T>;
+
+    fn into_iter(self) -> Iter<'r, 'a, T> {
+        self.iter()
+    }
+}
+
+/// 开启 futures feature 后，Sender 实现 Sink，Receiver 实现 Stream，可以直接在异步任务中使用.
+/// 队列满/空时，把当前任务的 Waker 登记在与阻塞等待相同的 send_waiter/recv_waiter 中，
+/// 所以阻塞的一端和异步的一端可以混用. 登记之后再检查一次条件，避免错过并发的推进.
+///
+/// Sender 不能 clone，poll_ready 需要 &mut self，同一时刻最多只有一个任务在等待空闲槽位，
+/// 所以只需要登记一个 Waker. 多个异步生产者应当使用 into_multi 得到的 MultiSender.
+///
+/// Receiver 被丢弃之后 poll_ready 和 start_send 返回 SendError(()): Sink 的错误类型不能带回消息，
+/// 交给 start_send 的消息在这里被释放. 需要取回消息时使用 send_async.
+#[cfg(feature = "futures")]
+impl<T> futures_sink::Sink<T> for Sender<'_, T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let channel = &*self.inner;
+        let ready = || channel.is_closed() || !channel.send_would_block();
+        if !ready() {
+            channel.send_waiter.register_waker(cx.waker());
+            if !ready() {
+                return Poll::Pending;
+            }
+            channel.send_waiter.unregister();
+        }
+        if channel.is_closed() {
+            return Poll::Ready(Err(SendError(())));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// # Panics
+    /// 没有先通过 poll_ready 确认有空闲槽位时 panic.
+    fn start_send(self: Pin<&mut Self>, msg: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if this.inner.is_closed() {
+            return Err(SendError(()));
+        }
+        assert!(
+            !this.inner.send_would_block(),
+            "start_send called without poll_ready"
+        );
+        this.inner.push(msg);
+        Ok(())
+    }
+
+    /// 消息在 start_send 中就已经对消费者可见，没有需要刷新的缓冲.
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<'a, T> Sender<'a, T> {
+    /// 换成可以 clone 的 MultiSender，交给多个异步生产者. 最后一个 MultiSender 被丢弃时
+    /// Receiver 才会收到 Disconnected.
+    pub fn into_multi(self) -> MultiSender<'a, T> {
+        let this = std::mem::ManuallyDrop::new(self);
+        // 通道的所有权转给 MultiSender，不运行 Sender 的 Drop.
+        let inner = unsafe { core::ptr::read(&this.inner) };
+        inner.send_queue.lock().unwrap().producers = 1;
+        MultiSender {
+            inner,
+            ticket: None,
+            reserved: false,
+        }
+    }
+}
+
+/// 由 Sender::into_multi 得到，可以 clone，只能通过 Sink 发送.
+/// 队列满时，等待空闲槽位的 MultiSender 按第一次 poll_ready 的先后在通道中排队(FIFO):
+///     1.Receiver 每空出槽位只唤醒排在最前面的一个，不会惊群. 被唤醒的一方预留槽位之后，
+///       还有空位时再唤醒下一个，recv_many 一次空出的多个槽位依次传下去；
+///     2.有人在排队时，新来的 poll_ready 即使看到空位也排到队尾，不会插队；
+///     3.poll_ready 返回 Ready(Ok) 时为自己预留一个槽位，start_send 使用它，
+///       所以两次调用之间别的 MultiSender 不会占走它.
+/// tail 由持有 send_queue 锁的一方推进，同一时刻只有一个 MultiSender 写入槽位.
+/// 等待期间丢弃 send 的 Future 而保留 MultiSender 时，它仍然占着队列中的位置，
+/// 直到下一次 poll_ready 或者 MultiSender 被丢弃.
+#[cfg(feature = "futures")]
+pub struct MultiSender<'a, T> {
+    inner: ChannelRef<'a, BoundedChannel<T>>,
+    // ticket : 排队时在 send_queue 中的编号，不在排队时为 None.
+    ticket: Option<u64>,
+    // reserved : poll_ready 已经为自己预留了一个槽位，还没有被 start_send 用掉.
+    reserved: bool,
+}
+
+#[cfg(feature = "futures")]
+impl<T> Clone for MultiSender<'_, T> {
+    fn clone(&self) -> Self {
+        self.inner.send_queue.lock().unwrap().producers += 1;
+        MultiSender {
+            inner: self.inner.clone(),
+            ticket: None,
+            reserved: false,
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> Drop for MultiSender<'_, T> {
+    /// 离开队列并归还预留的槽位，空出来的位置交给排在后面的生产者.
+    /// 最后一个 MultiSender 被丢弃时与 Sender 的 Drop 相同.
+    fn drop(&mut self) {
+        let channel = &*self.inner;
+        let mut queue = channel.send_queue.lock().unwrap();
+        if let Some(ticket) = self.ticket.take() {
+            queue.remove(ticket);
+        }
+        if self.reserved {
+            queue.reserved -= 1;
+        }
+        channel.queued.store(queue.waiters.len(), Ordering::Relaxed);
+        queue.producers -= 1;
+        let last = queue.producers == 0;
+        let next = channel.first_queued(&queue);
+        drop(queue);
+        if let Some(waker) = next {
+            waker.wake();
+        }
+        if last {
+            channel.disconnected.store(true, Ordering::Release);
+            channel.recv_waiter.unpark();
+        }
+    }
+}
+
+/// Receiver 被丢弃之后与 Sender 的 Sink 相同，返回 SendError(()).
+#[cfg(feature = "futures")]
+impl<T> futures_sink::Sink<T> for MultiSender<'_, T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.reserved {
+            return Poll::Ready(Ok(()));
+        }
+        let channel = &*this.inner;
+        let mut queue = channel.send_queue.lock().unwrap();
+        let ready = |queue: &SendQueue, ticket| {
+            channel.is_closed() || (queue.is_first(ticket) && channel.free_slots(queue) > 0)
+        };
+        if !ready(&queue, this.ticket) {
+            queue.enqueue(&mut this.ticket, cx.waker());
+            channel.queued.store(queue.waiters.len(), Ordering::Relaxed);
+            // 与 wake_queued 中的 fence 配对: 要么 Receiver 看到了新登记的等待方，
+            // 要么这里的再次检查看到了 Receiver 空出的槽位.
+            fence(Ordering::SeqCst);
+            if !ready(&queue, this.ticket) {
+                return Poll::Pending;
+            }
+        }
+        if let Some(ticket) = this.ticket.take() {
+            queue.remove(ticket);
+            channel.queued.store(queue.waiters.len(), Ordering::Relaxed);
+        }
+        if channel.is_closed() {
+            return Poll::Ready(Err(SendError(())));
+        }
+        queue.reserved += 1;
+        this.reserved = true;
+        let next = channel.first_queued(&queue);
+        drop(queue);
+        if let Some(waker) = next {
+            waker.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// # Panics
+    /// 没有先通过 poll_ready 预留槽位时 panic.
+    fn start_send(self: Pin<&mut Self>, msg: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let channel = &*this.inner;
+        if !this.reserved {
+            if channel.is_closed() {
+                return Err(SendError(()));
+            }
+            panic!("start_send called without poll_ready");
+        }
+        this.reserved = false;
+        let mut queue = channel.send_queue.lock().unwrap();
+        queue.reserved -= 1;
+        if channel.is_closed() {
+            return Err(SendError(()));
+        }
+        // 在锁内写入，同一时刻只有一个 MultiSender 推进 tail.
+        channel.push(msg);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// 排队等待空闲槽位的 MultiSender，以及它们共享的计数，都由 send_queue 的锁保护.
+#[cfg(feature = "futures")]
+struct SendQueue {
+    // waiters : 按登记顺序排列的 (ticket, Waker)，每个 MultiSender 最多占一项.
+    waiters: VecDeque<(u64, Waker)>,
+    next_ticket: u64,
+    // reserved : poll_ready 已经预留、还没有被 start_send 用掉的槽位数.
+    reserved: usize,
+    // producers : 存活的 MultiSender 数量.
+    producers: usize,
+}
+
+#[cfg(feature = "futures")]
+impl SendQueue {
+    fn new() -> Self {
+        Self {
+            waiters: VecDeque::new(),
+            next_ticket: 0,
+            reserved: 0,
+            producers: 0,
+        }
+    }
+
+    /// 是否轮到持有 ticket 的一方: 它排在最前面，或者它没有排队并且队列为空.
+    fn is_first(&self, ticket: Option<u64>) -> bool {
+        match ticket {
+            Some(ticket) => self.waiters.front().map(|(t, _)| *t) == Some(ticket),
+            None => self.waiters.is_empty(),
+        }
+    }
+
+    /// 没有排队时排到队尾，已经在排队时保留原来的位置，只更新 Waker.
+    fn enqueue(&mut self, ticket: &mut Option<u64>, waker: &Waker) {
+        if let Some(ticket) = *ticket {
+            if let Some((_, registered)) = self.waiters.iter_mut().find(|(t, _)| *t == ticket) {
+                if !registered.will_wake(waker) {
+                    *registered = waker.clone();
+                }
+                return;
+            }
+        }
+        let next = self.next_ticket;
+        self.next_ticket = next.wrapping_add(1);
+        self.waiters.push_back((next, waker.clone()));
+        *ticket = Some(next);
+    }
+
+    fn remove(&mut self, ticket: u64) {
+        self.waiters.retain(|(t, _)| *t != ticket);
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for Receiver<'_, T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        let channel = &*this.inner;
+        if channel.recv_would_block() && !channel.is_disconnected() {
+            channel.recv_waiter.register_waker(cx.waker());
+            if channel.recv_would_block() && !channel.is_disconnected() {
+                return Poll::Pending;
+            }
+            channel.recv_waiter.unregister();
+        }
+        // Sender 被丢弃之后读完剩下的消息，流随之结束.
+        Poll::Ready(this.try_recv().ok())
+    }
+}
+
+pub struct BoundedChannel<T> {
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // head : 下一个要读取的位置，只由 Receiver 推进.
+    head: AtomicUsize,
+    // tail : 下一个要写入的位置，只由 Sender(或持有 send_queue 锁的 MultiSender)推进.
+    tail: AtomicUsize,
+    // disconnected : 当前这一对的 Sender 是否已经被丢弃，split 时重置.
+    disconnected: AtomicBool,
+    // closed : 当前这一对的 Receiver 是否已经被丢弃，split 时重置.
+    closed: AtomicBool,
+    // 两端都可能停放，且 Sender 可以在线程之间移动，所以各自在停放前登记线程句柄.
+    send_waiter: Waiter,
+    recv_waiter: Waiter,
+    // 排队中的 MultiSender，见 MultiSender. queued 是其中等待方的数量，
+    // Receiver 据此在没有人排队时跳过加锁.
+    #[cfg(feature = "futures")]
+    send_queue: Mutex<SendQueue>,
+    #[cfg(feature = "futures")]
+    queued: AtomicUsize,
+}
+
+impl<T> BoundedChannel<T> {
+    /// # Panics
+    /// capacity 为 0 时 panic.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be non-zero");
+        Self {
+            buffer: (0..capacity)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            disconnected: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            send_waiter: Waiter::new(),
+            recv_waiter: Waiter::new(),
+            #[cfg(feature = "futures")]
+            send_queue: Mutex::new(SendQueue::new()),
+            #[cfg(feature = "futures")]
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// 与 One-Shot channel 的 split 一样，通过独占借用保证只有一个生产者和一个消费者.
+    /// 不会清空队列: 上一对 Sender/Receiver 留下的消息仍会被新的 Receiver 收到.
+    pub fn split(&mut self) -> (Sender<'_, T>, Receiver<'_, T>) {
+        // &mut self 之下没有并发访问，Relaxed 即可.
+        self.disconnected.store(false, Ordering::Relaxed);
+        self.closed.store(false, Ordering::Relaxed);
+        #[cfg(feature = "futures")]
+        {
+            *self.send_queue.lock().unwrap() = SendQueue::new();
+            self.queued.store(0, Ordering::Relaxed);
+        }
+        (
+            Sender {
+                inner: ChannelRef::Borrowed(self),
+            },
+            Receiver {
+                inner: ChannelRef::Borrowed(self),
+            },
+        )
+    }
+
+    /// 环形缓冲区的槽位数，即 new 时指定的 capacity.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// 队列中的消息数. head/tail 只增不减，tail - head 就是准确的消息数，不需要取模，
+    /// 也不会因为下标回绕把"满"误认为"空".
+    ///
+    /// 先读 head 再读 tail: 读到的 tail 不会早于读到的 head，差值不会为负. 两次读取之间
+    /// 对方可能又推进了自己的下标，所以结果只是这两次读取之间某一时刻的近似值，
+    /// 并截断到 capacity 以内. 在生产者或消费者线程上调用时，自己推进的那个下标是准确的，
+    /// 但对方的下标可能在返回之后立即改变: 这个值只适合用于监控和调试，不能用来代替 send / recv 的等待.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(self.capacity())
+    }
+
+    /// 与 len 相同，只是一个可能过时的快照.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 只应由 Sender 调用: tail 只有 Sender 自己会修改，因此可以用 Relaxed 读取.
+    fn send_would_block(&self) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        tail.wrapping_sub(self.head.load(Ordering::Acquire)) == self.capacity()
+    }
+
+    /// 只应由 Receiver 调用: head 只有 Receiver 自己会修改，因此可以用 Relaxed 读取.
+    fn recv_would_block(&self) -> bool {
+        self.tail.load(Ordering::Acquire) == self.head.load(Ordering::Relaxed)
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::Acquire)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    fn slot(&self, index: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.buffer[index % self.capacity()]
+    }
+
+    /// 把消息写入 tail 处的槽位. 调用方需要保证队列未满，并且此刻只有自己在推进 tail:
+    /// 持有 &mut Sender，或者持有 send_queue 锁的 MultiSender.
+    fn push(&self, msg: T) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        self.slot(tail).with_mut(|ptr| unsafe { (*ptr).write(msg) });
+        // Release: 消费者用 Acquire 读到新的 tail 时，也能看到上面写入的消息.
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        self.recv_waiter.unpark();
+    }
+
+    /// Receiver 空出槽位之后调用，唤醒等待中的 Sender，或者排在最前面的一个 MultiSender.
+    fn wake_sender(&self) {
+        self.send_waiter.unpark();
+        #[cfg(feature = "futures")]
+        if self.has_queued() {
+            let next = self.first_queued(&self.send_queue.lock().unwrap());
+            if let Some(waker) = next {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Receiver 被丢弃时调用，唤醒所有排队中的 MultiSender，让它们看到 closed.
+    #[cfg(feature = "futures")]
+    fn wake_all_queued(&self) {
+        if !self.has_queued() {
+            return;
+        }
+        let wakers: Vec<_> = (self.send_queue.lock().unwrap().waiters.iter())
+            .map(|(_, waker)| waker.clone())
+            .collect();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    #[cfg(feature = "futures")]
+    fn has_queued(&self) -> bool {
+        // 与 MultiSender::poll_ready 中的 fence 配对，见那里的注释.
+        fence(Ordering::SeqCst);
+        self.queued.load(Ordering::Relaxed) != 0
+    }
+
+    /// 还有没被预留的空位时，取出排在最前面的 Waker. 在锁外调用 wake，
+    /// 与 Waiter::unpark 一样，Waker 在 wake 中 panic 不会毒化 send_queue.
+    #[cfg(feature = "futures")]
+    fn first_queued(&self, queue: &SendQueue) -> Option<Waker> {
+        if self.free_slots(queue) == 0 {
+            return None;
+        }
+        queue.waiters.front().map(|(_, waker)| waker.clone())
+    }
+
+    /// 没有消息、也没有被预留的槽位数. 只应在持有 send_queue 锁时调用:
+    /// 这时只有自己会推进 tail，reserved 也不会改变.
+    #[cfg(feature = "futures")]
+    fn free_slots(&self, queue: &SendQueue) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let len = tail.wrapping_sub(self.head.load(Ordering::Acquire));
+        self.capacity().saturating_sub(len + queue.reserved)
+    }
+}
+
+unsafe impl<T> Sync for BoundedChannel<T> where T: Send {}
+
+impl<T> Drop for BoundedChannel<T> {
+    /// 释放 head 与 tail 之间还没有被取走的消息. &mut self 之下没有并发访问，Relaxed 即可.
+    fn drop(&mut self) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let mut index = self.head.load(Ordering::Relaxed);
+        while index != tail {
+            self.slot(index)
+                .with_mut(|ptr| unsafe { (*ptr).assume_init_drop() });
+            index = index.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::rc::Rc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut channel = BoundedChannel::new(4);
+        thread::scope(|s| {
+            let (mut sender, mut receiver) = channel.split();
+            s.spawn(move || {
+                for i in 0..1000 {
+                    sender.send(i).unwrap();
+                }
+            });
+            for i in 0..1000 {
+                assert_eq!(receiver.recv(), Ok(i));
+            }
+        });
+    }
+
+    #[test]
+    fn drop_unreceived() {
+        let msg = Rc::new(());
+        let mut channel = BoundedChannel::new(4);
+        let (mut sender, mut receiver) = channel.split();
+        for _ in 0..4 {
+            sender.send(msg.clone()).unwrap();
+        }
+        drop(receiver.recv());
+        assert_eq!(Rc::strong_count(&msg), 4);
+        assert_eq!(sender.len(), 3);
+        drop((sender, receiver));
+        drop(channel);
+        assert_eq!(Rc::strong_count(&msg), 1);
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn sink_and_stream() {
+        use futures::{executor::block_on, SinkExt, StreamExt};
+
+        let mut channel = BoundedChannel::new(2);
+        thread::scope(|s| {
+            let (mut sender, receiver) = channel.split();
+            s.spawn(move || {
+                block_on(async {
+                    for i in 0..100 {
+                        SinkExt::send(&mut sender, i).await.unwrap();
+                    }
+                })
+            });
+            let received: Vec<_> = block_on(receiver.take(100).collect());
+            assert_eq!(received, (0..100).collect::<Vec<_>>());
+        });
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn sink_reports_dropped_receiver() {
+        use futures::{executor::block_on, Sink, SinkExt};
+
+        // 队列已满时 Receiver 被丢弃: 等待中的 poll_ready 被唤醒并返回错误，而不是永远 Pending.
+        let (mut sender, receiver) = channel(1);
+        block_on(SinkExt::send(&mut sender, 0)).unwrap();
+        let handle = thread::spawn(move || block_on(SinkExt::send(&mut sender, 1)));
+        drop(receiver);
+        assert_eq!(handle.join().unwrap(), Err(SendError(())));
+
+        // 队列还有空位时 Receiver 被丢弃: start_send 不会假装成功.
+        let (mut sender, receiver) = channel::<i32>(4);
+        drop(receiver);
+        assert_eq!(block_on(SinkExt::send(&mut sender, 2)), Err(SendError(())));
+        assert_eq!(Pin::new(&mut sender).start_send(3), Err(SendError(())));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn many_async_producers_into_capacity_one() {
+        use futures::executor::block_on;
+        use futures::SinkExt;
+
+        const PRODUCERS: usize = 4;
+        const EACH: usize = 20;
+
+        let (sender, mut receiver) = channel(1);
+        let consumer = thread::spawn(move || {
+            let mut received = Vec::new();
+            while let Ok(msg) = receiver.recv() {
+                // 慢速的消费者，生产者大部分时间都在排队等待空闲槽位.
+                if received.len() % 10 == 0 {
+                    thread::sleep(std::time::Duration::from_millis(1));
+                }
+                received.push(msg);
+            }
+            received
+        });
+
+        let sender = sender.into_multi();
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let mut sender = sender.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        for i in 0..EACH {
+                            SinkExt::send(&mut sender, p * EACH + i).await.unwrap();
+                        }
+                    })
+                })
+            })
+            .collect();
+        // 最后一个 MultiSender 被丢弃，消费者在读完之后收到 Disconnected.
+        drop(sender);
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut received = consumer.join().unwrap();
+        // 同一个生产者的消息保持顺序.
+        for p in 0..PRODUCERS {
+            let own: Vec<_> = received.iter().filter(|&&m| m / EACH == p).collect();
+            assert!(own.windows(2).all(|w| w[0] < w[1]));
+        }
+        received.sort();
+        assert_eq!(received, (0..PRODUCERS * EACH).collect::<Vec<_>>());
+    }
+
+    /// 记录自己被唤醒的 Waker，用来检查唤醒的顺序.
+    #[cfg(feature = "futures")]
+    struct RecordWake(usize, Arc<std::sync::Mutex<Vec<usize>>>);
+
+    #[cfg(feature = "futures")]
+    impl std::task::Wake for RecordWake {
+        fn wake(self: Arc<Self>) {
+            self.1.lock().unwrap().push(self.0);
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn multi_sender_wakes_in_fifo_order() {
+        use futures::{executor::block_on, FutureExt, SinkExt};
+
+        let (sender, mut receiver) = channel(1);
+        let mut first = sender.into_multi();
+        block_on(SinkExt::send(&mut first, 0)).unwrap();
+
+        let woken = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut senders: Vec<_> = (0..3).map(|_| first.clone()).collect();
+        let mut sends: Vec<_> = senders
+            .iter_mut()
+            .enumerate()
+            .map(|(i, sender)| SinkExt::send(sender, i + 1))
+            .collect();
+        let wakers: Vec<_> = (0..3)
+            .map(|i| Waker::from(Arc::new(RecordWake(i, woken.clone()))))
+            .collect();
+        // 按 0, 1, 2 的顺序排队.
+        for (send, waker) in sends.iter_mut().zip(&wakers) {
+            assert!(send
+                .poll_unpin(&mut Context::from_waker(waker))
+                .is_pending());
+        }
+        // 排队时新来的 poll_ready 不会插队.
+        let mut late = first.clone();
+        let mut late_send = SinkExt::send(&mut late, 4);
+        let late_waker = Waker::from(Arc::new(RecordWake(3, woken.clone())));
+        assert!(late_send
+            .poll_unpin(&mut Context::from_waker(&late_waker))
+            .is_pending());
+
+        for i in 0..4 {
+            assert_eq!(receiver.try_recv(), Ok(i));
+            // 每空出一个槽位只唤醒排在最前面的那一个.
+            assert_eq!(*woken.lock().unwrap(), [i]);
+            woken.lock().unwrap().clear();
+            let cx = &mut Context::from_waker(if i < 3 { &wakers[i] } else { &late_waker });
+            let send = if i < 3 { &mut sends[i] } else { &mut late_send };
+            assert_eq!(send.poll_unpin(cx), Poll::Ready(Ok(())));
+        }
+        assert_eq!(receiver.try_recv(), Ok(4));
+        assert!(woken.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn multi_sender_disconnects_with_last_clone() {
+        use futures::{executor::block_on, FutureExt, SinkExt};
+
+        let (sender, mut receiver) = channel(1);
+        let mut first = sender.into_multi();
+        let mut second = first.clone();
+        block_on(SinkExt::send(&mut first, 0)).unwrap();
+        drop(first);
+        // 还有一个 MultiSender 存活，没有 Disconnected.
+        assert_eq!(receiver.try_recv(), Ok(0));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        block_on(SinkExt::send(&mut second, 1)).unwrap();
+        let third = second.clone();
+        drop(second);
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        drop(third);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+
+        // 排队中的 MultiSender 在 Receiver 被丢弃时全部被唤醒并返回错误.
+        let (sender, receiver) = channel(1);
+        let mut second = sender.into_multi();
+        block_on(SinkExt::send(&mut second, 0)).unwrap();
+        let mut third = second.clone();
+        let mut sends = [SinkExt::send(&mut second, 2), SinkExt::send(&mut third, 3)];
+        let woken = Arc::new(std::sync::Mutex::new(Vec::new()));
+        for (i, send) in sends.iter_mut().enumerate() {
+            let waker = Waker::from(Arc::new(RecordWake(i, woken.clone())));
+            assert!(send
+                .poll_unpin(&mut Context::from_waker(&waker))
+                .is_pending());
+        }
+        drop(receiver);
+        assert_eq!(*woken.lock().unwrap(), [0, 1]);
+        for send in &mut sends {
+            assert_eq!(block_on(send), Err(SendError(())));
+        }
+    }
+
+    #[test]
+    fn capacity_and_len() {
+        let mut channel = BoundedChannel::new(3);
+        assert_eq!(channel.capacity(), 3);
+        assert_eq!(channel.len(), 0);
+        let (mut sender, mut receiver) = channel.split();
+        // 多转几圈，确认下标回绕之后 len 仍然正确.
+        for round in 0..5 {
+            for i in 0..3 {
+                sender.send(round * 3 + i).unwrap();
+                assert_eq!(sender.len(), i + 1);
+            }
+            assert_eq!(receiver.len(), 3);
+            for i in 0..3 {
+                assert_eq!(receiver.recv(), Ok(round * 3 + i));
+                assert_eq!(receiver.len(), 2 - i);
+            }
+        }
+        assert_eq!(receiver.capacity(), 3);
+    }
+
+    // 依赖 sleep 期间另一个线程已经运行到第三次 send，Miri 下执行太慢，这个假设不成立.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn full_and_empty_boundaries() {
+        use std::sync::atomic::AtomicBool;
+        use std::time::Duration;
+
+        let (mut sender, mut receiver) = channel(2);
+        let sent_third = Arc::new(AtomicBool::new(false));
+        let flag = sent_third.clone();
+        let handle = thread::spawn(move || {
+            sender.send(0).unwrap();
+            sender.send(1).unwrap();
+            // 队列已满，这一次 send 要等到 Receiver 取走一条消息.
+            sender.send(2).unwrap();
+            flag.store(true, Ordering::Release);
+            sender
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert!(!sent_third.load(Ordering::Acquire));
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.recv(), Ok(0));
+        let mut sender = handle.join().unwrap();
+        assert!(sent_third.load(Ordering::Acquire));
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(receiver.recv(), Ok(2));
+        assert!(receiver.is_empty());
+
+        // 队列为空时 recv 阻塞，直到下一条消息到达.
+        let handle = thread::spawn(move || receiver.recv());
+        thread::sleep(Duration::from_millis(10));
+        sender.send(3).unwrap();
+        assert_eq!(handle.join().unwrap(), Ok(3));
+    }
+
+    #[test]
+    fn capacity_one_hands_over_each_message() {
+        let (mut sender, mut receiver) = channel(1);
+        let handle = thread::spawn(move || {
+            for i in 0..100 {
+                sender.send(i).unwrap();
+            }
+        });
+        for i in 0..100 {
+            assert_eq!(receiver.recv(), Ok(i));
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn iterate_until_disconnected() {
+        let (mut sender, receiver) = channel(2);
+        let handle = thread::spawn(move || {
+            for i in 0..10 {
+                sender.send(i).unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        for msg in receiver {
+            received.push(msg);
+        }
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+        handle.join().unwrap();
+
+        let (mut sender, mut receiver) = channel(4);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), [1, 2]);
+        sender.send(3).unwrap();
+        drop(sender);
+        assert_eq!(receiver.iter().collect::<Vec<_>>(), [3]);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_fails_after_receiver_dropped_when_full() {
+        let (mut sender, receiver) = channel(2);
+        sender.send(0).unwrap();
+        sender.send(1).unwrap();
+        // 队列已满: 无论 Receiver 在 send 停放之前还是之后被丢弃，send 都会返回而不是永远阻塞.
+        let handle = thread::spawn(move || {
+            let result = sender.send(2);
+            (result, sender)
+        });
+        drop(receiver);
+        let (result, mut sender) = handle.join().unwrap();
+        assert_eq!(result, Err(SendError(2)));
+        // 之后的 send 立即失败.
+        assert_eq!(sender.send(3), Err(SendError(3)));
+    }
+
+    #[test]
+    fn recv_many_drains_in_one_pass() {
+        let (mut sender, mut receiver) = channel(4);
+        let mut buf = vec![0];
+        assert_eq!(receiver.recv_many(&mut buf, 0), 0);
+        for i in 1..=3 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.recv_many(&mut buf, 2), 2);
+        assert_eq!(receiver.recv_many(&mut buf, 10), 1);
+        assert_eq!(buf, [0, 1, 2, 3]);
+
+        // 阻塞到第一条消息到达.
+        let handle = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(5));
+            sender.send(4).unwrap();
+        });
+        buf.clear();
+        assert_eq!(receiver.recv_many(&mut buf, 10), 1);
+        handle.join().unwrap();
+        assert_eq!(buf, [4]);
+        // Sender 已经被丢弃，队列为空.
+        assert_eq!(receiver.recv_many(&mut buf, 10), 0);
+    }
+
+    #[test]
+    fn send_timeout_returns_message() {
+        let (mut sender, mut receiver) = channel(1);
+        sender.send(String::from("first")).unwrap();
+        let start = std::time::Instant::now();
+        let err = sender
+            .send_timeout(String::from("second"), Duration::from_millis(20))
+            .unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(matches!(&err, SendTimeoutError::Timeout(msg) if msg == "second"));
+
+        // 消费者取走一条消息之后，等待中的 send_timeout 被唤醒并完成发送.
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(5));
+            assert_eq!(receiver.recv().as_deref(), Ok("first"));
+            receiver
+        });
+        sender
+            .send_timeout(err.into_inner(), Duration::from_secs(10))
+            .unwrap();
+        let receiver = handle.join().unwrap();
+        drop(receiver);
+        assert!(matches!(
+            sender.send_timeout(String::from("third"), Duration::from_secs(10)),
+            Err(SendTimeoutError::Disconnected(msg)) if msg == "third"
+        ));
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+
+    use super::*;
+
+    /// capacity 为 1 时每条消息都写入上一条消息所在的槽位: 队列已满时 send 停放，只有 Receiver
+    /// 读完槽位并用 Release 推进 head 之后，Sender 才能覆盖它. loom 检查同一个槽位上的读和随后的写
+    /// 之间总是有同步，并且停放的一方总会被唤醒.
+    #[test]
+    fn push_pop_wraparound() {
+        loom::model(|| {
+            let (mut sender, mut receiver) = channel(1);
+            sender.send(0).unwrap();
+            let producer = thread::spawn(move || sender.send(1).unwrap());
+            let consumer = thread::spawn(move || [receiver.recv(), receiver.recv()]);
+            producer.join().unwrap();
+            assert_eq!(consumer.join().unwrap(), [Ok(0), Ok(1)]);
+        });
+    }
+}
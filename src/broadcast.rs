@@ -0,0 +1,395 @@
+//! broadcast channel: 每条消息都会被送到发送时存在的所有 Receiver.
+//! 所有消息放在一个由 Mutex 保护的环形缓冲区(VecDeque)中，每个 Receiver 只记录自己的读取位置:
+//!     1.消息按发送顺序编号，head 是缓冲区中最旧一条消息的编号，tail 是下一条消息的编号；
+//!     2.缓冲区满了之后，新消息挤掉最旧的消息，head 随之前进. 读取位置落后于 head 的 Receiver
+//!       已经错过了一些消息，下一次 recv 返回 RecvError::Lagged(错过的条数)，并跳到最旧的消息继续读；
+//!     3.Receiver 的数量不定，send 之后用 Condvar 的 notify_all 唤醒所有等待者.
+//! 每条消息记录还有几个 Receiver 没有读过它(发送时存在的 Receiver 数)，最后一个读取者直接取得消息本身，
+//! 其余的读取者得到一份 clone；被所有 Receiver 读过的消息立即离开缓冲区.
+//! 缓冲区满了之后怎么办由 channel_with 的 Overflow 决定:
+//!     1.Lag(channel 的默认值): 如上所述挤掉最旧的消息，send 从不阻塞，慢的 Receiver 收到 Lagged；
+//!     2.Block: send 阻塞，直到最慢的 Receiver 读完最旧的消息，每个 Receiver 都不会错过任何消息.
+//! 丢弃一个 Receiver 会替它"读完"所有还没读过的消息，所以它不会让 Block 模式的 Sender 永远等下去.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::SendError;
+
+/// 创建一个最多缓存 capacity 条消息的 broadcast channel.
+/// 返回的 Receiver 与之后通过 Sender::subscribe 得到的 Receiver 一样，只能收到创建之后发送的消息.
+///
+/// # Panics
+/// capacity 为 0 时 panic.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with(capacity, Overflow::Lag)
+}
+
+/// 缓冲区已满时 send 的行为.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// 挤掉最旧的消息，还没读过它的 Receiver 下一次 recv 收到 RecvError::Lagged.
+    #[default]
+    Lag,
+    /// 阻塞 send，直到所有 Receiver 都读过最旧的消息.
+    Block,
+}
+
+/// 与 channel 相同，但缓冲区已满时按 overflow 处理.
+///
+/// # Panics
+/// capacity 为 0 时 panic.
+pub fn channel_with<T: Clone>(capacity: usize, overflow: Overflow) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "capacity must be non-zero");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        changed: Condvar::new(),
+        capacity,
+        overflow,
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, next: 0 },
+    )
+}
+
+/// broadcast 的 recv 失败的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// 所有 Sender 都已被丢弃，并且这个 Receiver 已经读完了缓冲区中剩下的消息.
+    Disconnected,
+    /// 这个 Receiver 落后太多，有 n 条消息在被读取之前就被挤出了缓冲区.
+    /// 读取位置已经跳到最旧的那条消息，再次 recv 可以继续读取.
+    Lagged(u64),
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Disconnected => "receiving on a closed channel".fmt(f),
+            RecvError::Lagged(n) => write!(f, "receiver lagged behind and missed {n} messages"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    changed: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+struct State<T> {
+    // 每条消息和还没读过它的 Receiver 数.
+    buffer: VecDeque<(T, usize)>,
+    // head : buffer[0] 的编号. buffer 中的消息编号依次为 head..tail.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> State<T> {
+    // tail : 下一条消息的编号.
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+
+    /// 释放开头那些已经被所有 Receiver 读过的消息，返回是否释放了任何消息.
+    fn release_read(&mut self) -> bool {
+        let mut released = false;
+        while self.buffer.front().is_some_and(|&(_, unread)| unread == 0) {
+            self.buffer.pop_front();
+            self.head += 1;
+            released = true;
+        }
+        released
+    }
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 把消息放入缓冲区，并唤醒所有等待中的 Receiver. 缓冲区已满时，Lag 模式挤掉最旧的消息，
+    /// Block 模式阻塞到最慢的 Receiver 读完最旧的消息.
+    /// 没有任何 Receiver 时(包括阻塞期间所有 Receiver 都被丢弃)不会缓存消息，而是通过 SendError 交还给调用者.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.receivers != 0
+            && state.buffer.len() == self.shared.capacity
+            && self.shared.overflow == Overflow::Block
+        {
+            state = self.shared.changed.wait(state).unwrap();
+        }
+        if state.receivers == 0 {
+            return Err(SendError(msg));
+        }
+        if state.buffer.len() == self.shared.capacity {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+        let receivers = state.receivers;
+        state.buffer.push_back((msg, receivers));
+        drop(state);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// 创建一个新的 Receiver，它会收到从现在开始发送的每一条消息.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 最后一个 Sender 被丢弃时唤醒所有 Receiver，读完剩下的消息之后它们会收到 Disconnected.
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // next : 这个 Receiver 下一条要读取的消息的编号.
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    /// 阻塞直到有一条还没读过的消息，返回它的 clone.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if self.next < state.head {
+                let missed = state.head - self.next;
+                self.next = state.head;
+                return Err(RecvError::Lagged(missed));
+            }
+            if self.next < state.tail() {
+                let index = (self.next - state.head) as usize;
+                self.next += 1;
+                let (msg, unread) = &mut state.buffer[index];
+                *unread -= 1;
+                if index != 0 || *unread != 0 {
+                    return Ok(msg.clone());
+                }
+                // 最后一个读取最旧消息的 Receiver 直接取走它，不需要 clone.
+                let (msg, _) = state.buffer.pop_front().unwrap();
+                state.head += 1;
+                state.release_read();
+                drop(state);
+                self.shared.changed.notify_all();
+                return Ok(msg);
+            }
+            if state.senders == 0 {
+                return Err(RecvError::Disconnected);
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+}
+
+/// 与 Sender::subscribe 相同: 新的 Receiver 从当前的 tail 开始，只会收到 clone 之后发送的消息，
+/// 不会继承原 Receiver 还没读过的那些. 读取位置在锁内登记，与并发的 send 之间没有空隙.
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    /// 替这个 Receiver 读完它还没读过的消息，Block 模式的 Sender 不必再等它.
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receivers -= 1;
+        let start = self.next.saturating_sub(state.head) as usize;
+        for (_, unread) in state.buffer.iter_mut().skip(start) {
+            *unread -= 1;
+        }
+        if state.release_read() || state.receivers == 0 {
+            drop(state);
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn every_receiver_sees_every_message() {
+        let (sender, receiver) = channel(16);
+        let receivers: Vec<_> = std::iter::once(receiver)
+            .chain((0..3).map(|_| sender.subscribe()))
+            .collect();
+        let handles: Vec<_> = receivers
+            .into_iter()
+            .map(|mut receiver| {
+                thread::spawn(move || {
+                    let mut received = Vec::new();
+                    while let Ok(msg) = receiver.recv() {
+                        received.push(msg);
+                    }
+                    received
+                })
+            })
+            .collect();
+        for i in 0..10 {
+            sender.send(i).unwrap();
+        }
+        drop(sender);
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn lagged_receiver() {
+        let (sender, mut receiver) = channel(2);
+        for i in 0..5 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.recv(), Err(RecvError::Lagged(3)));
+        assert_eq!(receiver.recv(), Ok(3));
+        assert_eq!(receiver.recv(), Ok(4));
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn subscribe_sees_only_later_messages() {
+        let (sender, mut first) = channel(4);
+        sender.send(1).unwrap();
+        let mut second = sender.subscribe();
+        sender.send(2).unwrap();
+        assert_eq!(first.recv(), Ok(1));
+        assert_eq!(first.recv(), Ok(2));
+        assert_eq!(second.recv(), Ok(2));
+
+        drop(first);
+        drop(second);
+        assert_eq!(sender.send(3), Err(SendError(3)));
+    }
+
+    /// 接收到 Disconnected 为止，检查收到的消息和 Lagged 报告的条数恰好覆盖 0..total.
+    fn drain_with_lag_accounting(mut receiver: Receiver<u64>, delay: Duration) -> (u64, u64) {
+        let (mut next, mut received, mut lagged) = (0, 0, 0);
+        loop {
+            match receiver.recv() {
+                Ok(msg) => {
+                    assert_eq!(msg, next);
+                    next += 1;
+                    received += 1;
+                }
+                Err(RecvError::Lagged(n)) => {
+                    next += n;
+                    lagged += n;
+                }
+                Err(RecvError::Disconnected) => return (received, lagged),
+            }
+            thread::sleep(delay);
+        }
+    }
+
+    #[test]
+    fn fast_and_slow_consumers() {
+        const TOTAL: u64 = 200;
+        for overflow in [Overflow::Lag, Overflow::Block] {
+            let (sender, fast) = channel_with(4, overflow);
+            let slow = fast.clone();
+            let fast = thread::spawn(move || drain_with_lag_accounting(fast, Duration::ZERO));
+            let slow =
+                thread::spawn(move || drain_with_lag_accounting(slow, Duration::from_micros(200)));
+            for i in 0..TOTAL {
+                sender.send(i).unwrap();
+            }
+            drop(sender);
+            for handle in [fast, slow] {
+                let (received, lagged) = handle.join().unwrap();
+                assert_eq!(received + lagged, TOTAL);
+                if overflow == Overflow::Block {
+                    assert_eq!(lagged, 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn block_waits_for_slowest() {
+        let (sender, mut fast) = channel_with(2, Overflow::Block);
+        let slow = fast.clone();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(fast.recv(), Ok(1));
+        assert_eq!(fast.recv(), Ok(2));
+        // slow 还没读过 1，缓冲区仍然是满的: send 阻塞到 slow 被丢弃为止.
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            drop(slow);
+        });
+        sender.send(3).unwrap();
+        handle.join().unwrap();
+        assert_eq!(fast.recv(), Ok(3));
+        drop(fast);
+        assert_eq!(sender.send(4), Err(SendError(4)));
+    }
+
+    #[test]
+    fn clone_starts_at_tail() {
+        let (sender, mut first) = channel(2);
+        sender.send(1).unwrap();
+        let mut second = first.clone();
+        sender.send(2).unwrap();
+        sender.send(3).unwrap();
+        // first 还没读过 1，它已经被挤出缓冲区；second 从 clone 时的 tail 开始，没有落后.
+        assert_eq!(first.recv(), Err(RecvError::Lagged(1)));
+        assert_eq!(second.recv(), Ok(2));
+        assert_eq!(second.recv(), Ok(3));
+
+        // 只剩 clone 出来的 Receiver 时仍然可以发送.
+        drop(first);
+        sender.send(4).unwrap();
+        assert_eq!(second.recv(), Ok(4));
+        drop(second);
+        assert_eq!(sender.send(5), Err(SendError(5)));
+    }
+}
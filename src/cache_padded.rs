@@ -0,0 +1,49 @@
+//! 把值对齐并填充到一整条缓存行，避免与相邻的字段发生伪共享(false sharing).
+//! 关闭 cache-padded feature 时 CachePadded 只是一个透明的包装，不改变对齐和大小.
+
+use core::ops::{Deref, DerefMut};
+
+/// x86_64 和大多数 ARM 核心的缓存行都是 64 字节.
+#[cfg_attr(feature = "cache-padded", repr(align(64)))]
+pub(crate) struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self { value }
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use core::mem;
+
+    use super::*;
+    use crate::Channel;
+
+    #[test]
+    fn layout() {
+        if cfg!(feature = "cache-padded") {
+            assert_eq!(mem::align_of::<CachePadded<u8>>(), 64);
+            // 消息独占第一条缓存行，状态字段从第二条开始.
+            assert!(mem::size_of::<Channel<u8>>() >= 128);
+        } else {
+            assert_eq!(mem::size_of::<CachePadded<u8>>(), 1);
+        }
+    }
+}
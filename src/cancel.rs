@@ -0,0 +1,185 @@
+//! 由第三方线程中止一次阻塞的接收，例如工作线程在等待回复时收到了关闭信号.
+//! CancelToken 内部是一个共享的 cancelled 标志和一个 Waiter:
+//!     1.recv_cancellable 同时登记在 Channel 的 waiter 和 CancelToken 的 waiter 中，
+//!       send 和 cancel 都能唤醒它；
+//!     2.每次醒来都先检查消息，再检查 cancelled，所以与 cancel 同时到达的消息会被返回；
+//!     3.因为 cancel 而返回时不会清除 ready，稍后到达的消息留在 Channel 中，
+//!       对 split() 得到的通道可以用 Channel::take 取回，不会被悄悄丢弃.
+
+use core::sync::atomic::Ordering;
+
+use crate::error::RecvCancellableError;
+use crate::sync::{thread, Arc, AtomicBool};
+use crate::waiter::Waiter;
+use crate::{Receiver, RecvError, TryRecvError};
+
+/// 可以被 clone 并发送到其他线程的取消令牌，由 Receiver::cancel_token 得到.
+/// 同一时刻只应有一个 recv_cancellable 在等待同一个令牌: Waiter 只登记一个线程.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    inner: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    cancelled: AtomicBool,
+    waiter: Waiter,
+}
+
+impl CancelToken {
+    /// 请求取消: 正在等待的 recv_cancellable 会被唤醒并返回 Cancelled，之后的调用也会立即返回.
+    /// 可以多次调用，也可以在 recv_cancellable 开始之前调用.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.waiter.unpark();
+    }
+
+    /// 是否已经调用过 cancel.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Receiver<'_, T> {
+    /// 创建一个新的 CancelToken，交给其他线程后用于中止 recv_cancellable.
+    pub fn cancel_token(&self) -> CancelToken {
+        CancelToken {
+            inner: Arc::new(State {
+                cancelled: AtomicBool::new(false),
+                waiter: Waiter::new(),
+            }),
+        }
+    }
+
+    /// 与 recv 相同地阻塞等待消息，但 token 被 cancel 时提前返回 RecvCancellableError::Cancelled.
+    /// 消息与 cancel 同时到达时返回消息. 因为 cancel 返回之后才到达的消息留在通道中(见模块文档).
+    ///
+    /// # Panics
+    /// 与 recv 相同，消息已经通过 try_recv 取走后调用会 panic.
+    pub fn recv_cancellable(mut self, token: &CancelToken) -> Result<T, RecvCancellableError> {
+        assert!(!self.received, "message already received by try_recv");
+        fn check<T>(
+            rx: &mut Receiver<'_, T>,
+            token: &CancelToken,
+        ) -> Option<Result<T, RecvCancellableError>> {
+            match rx.try_recv() {
+                Ok(msg) => Some(Ok(msg)),
+                Err(TryRecvError::Disconnected) => Some(Err(RecvError::Disconnected.into())),
+                Err(TryRecvError::Cancelled) => Some(Err(RecvError::Cancelled.into())),
+                Err(TryRecvError::Empty) if token.is_cancelled() => {
+                    Some(Err(RecvCancellableError::Cancelled))
+                }
+                Err(TryRecvError::Empty) => None,
+            }
+        }
+        if let Some(result) = check(&mut self, token) {
+            return result;
+        }
+        self.inner.waiter.register();
+        token.inner.waiter.register();
+        let result = loop {
+            if let Some(result) = check(&mut self, token) {
+                break result;
+            }
+            thread::park();
+        };
+        token.inner.waiter.unregister();
+        self.inner.waiter.unregister();
+        result
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{channel, Channel};
+
+    #[test]
+    fn cancel_before_wait() {
+        let (_sender, receiver) = channel::<i32>();
+        let token = receiver.cancel_token();
+        token.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancellableError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn cancel_during_wait() {
+        let (_sender, receiver) = channel::<i32>();
+        let token = receiver.cancel_token();
+        let canceller = token.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancellableError::Cancelled)
+        );
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_after_send() {
+        let (sender, receiver) = channel();
+        let token = receiver.cancel_token();
+        sender.send(7).unwrap();
+        token.cancel();
+        // 消息先到达，cancel 不会使它丢失.
+        assert_eq!(receiver.recv_cancellable(&token), Ok(7));
+
+        let (sender, receiver) = channel::<i32>();
+        drop(sender);
+        let token = receiver.cancel_token();
+        assert_eq!(
+            receiver.recv_cancellable(&token),
+            Err(RecvCancellableError::Recv(RecvError::Disconnected))
+        );
+    }
+
+    #[test]
+    fn message_after_cancel_can_be_taken() {
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            let token = receiver.cancel_token();
+            token.cancel();
+            s.spawn(move || {
+                assert_eq!(
+                    receiver.recv_cancellable(&token),
+                    Err(RecvCancellableError::Cancelled)
+                );
+            })
+            .join()
+            .unwrap();
+            // Receiver 已经被丢弃，send 把消息交还给调用者.
+            assert_eq!(sender.send(1).unwrap_err().into_inner(), 1);
+        });
+        assert_eq!(channel.take(), None);
+
+        // cancel 与 send 竞争: 发送成功的消息要么被返回，要么留在通道中.
+        for _ in 0..20 {
+            let mut channel = Channel::new();
+            let (received, sent) = thread::scope(|s| {
+                let (sender, receiver) = channel.split();
+                let token = receiver.cancel_token();
+                let canceller = token.clone();
+                let handle = s.spawn(move || receiver.recv_cancellable(&token).ok());
+                s.spawn(move || canceller.cancel());
+                let sent = s.spawn(move || sender.send(2).is_ok());
+                (handle.join().unwrap(), sent.join().unwrap())
+            });
+            let left = channel.take();
+            match (sent, received, left) {
+                (true, Some(2), None) | (true, None, Some(2)) | (false, None, None) => {}
+                other => panic!("message lost or duplicated: {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! channel 的错误类型. 命名和 Display 文本与 std::sync::mpsc 保持一致，
+//! 熟悉标准库的调用方可以直接用同样的方式匹配和处理它们.
+//! 与 std::sync::mpsc 相同，这些错误都不是由其他错误引起的，source() 返回 None；
+//! 包装了 RecvError 的 RecvCancellableError::Recv 是透明的，Display 和 source() 都转发给内部的错误，
+//! 打印错误链时同一句话不会出现两次.
+
+use core::error::Error;
+use core::fmt;
+
+/// send 失败时返回的错误: Receiver 已经被丢弃，原消息原样交还给调用者，以便复用.
+/// 与 std::sync::mpsc::SendError 相同，Debug 不打印消息本身，所以不要求 T: Debug.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> SendError<T> {
+    /// 取回没有发送出去的消息.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a closed channel".fmt(f)
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// try_send 失败的原因，两种情况都把原消息交还给调用者. 命名与 std::sync::mpsc::TrySendError 相同.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// 现在无法放入消息: 对 One-Shot channel 来说，另一个 Sender 已经发送了这一次的消息.
+    Full(T),
+    /// Receiver 已经被丢弃(或者 Sender 调用了 cancel)，消息永远不会被接收.
+    Disconnected(T),
+}
+
+impl<T> TrySendError<T> {
+    /// 取回没有发送出去的消息.
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(msg) | TrySendError::Disconnected(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("Full(..)"),
+            TrySendError::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => "sending on a full channel".fmt(f),
+            TrySendError::Disconnected(_) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(SendError(msg): SendError<T>) -> Self {
+        TrySendError::Disconnected(msg)
+    }
+}
+
+/// send_timeout 失败的原因，两种情况都把原消息交还给调用者，可以重试或记录下来.
+/// Display 文本与 std::sync::mpsc::SendTimeoutError 相同；Debug 同样不打印消息本身.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    /// 在超时之前一直没有空闲的槽位. Receiver 仍然存活，稍后可以重试.
+    Timeout(T),
+    /// Receiver 已经被丢弃.
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    /// 取回没有发送出去的消息.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(msg) | SendTimeoutError::Disconnected(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => f.write_str("Timeout(..)"),
+            SendTimeoutError::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => "timed out waiting on send operation".fmt(f),
+            SendTimeoutError::Disconnected(_) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+/// recv 失败的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// Sender 未发送消息就被丢弃(例如发送线程 panic 或提前返回).
+    Disconnected,
+    /// Sender 通过 cancel 明确表示不会再有消息. 与 Disconnected 不同，这是正常的控制流.
+    Cancelled,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Disconnected => "receiving on a closed channel".fmt(f),
+            RecvError::Cancelled => "receiving on a cancelled channel".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+/// try_recv 失败的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// 消息尚未到达(或已经被取走).
+    Empty,
+    /// Sender 未发送消息就被丢弃，消息永远不会到达.
+    Disconnected,
+    /// Sender 调用了 cancel，消息永远不会到达.
+    Cancelled,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => "receiving on an empty channel".fmt(f),
+            TryRecvError::Disconnected => "receiving on an empty and disconnected channel".fmt(f),
+            TryRecvError::Cancelled => "receiving on a cancelled channel".fmt(f),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Disconnected => TryRecvError::Disconnected,
+            RecvError::Cancelled => TryRecvError::Cancelled,
+        }
+    }
+}
+
+/// recv_timeout / recv_deadline 失败的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    /// 在截止时间之前消息没有到达. 消息仍可能稍后到达，Receiver 可以继续等待.
+    Timeout,
+    /// Sender 未发送消息就被丢弃.
+    Disconnected,
+    /// Sender 调用了 cancel.
+    Cancelled,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => "timed out waiting on channel".fmt(f),
+            RecvTimeoutError::Disconnected => "channel is empty and sending half is closed".fmt(f),
+            RecvTimeoutError::Cancelled => "channel was cancelled by the sending half".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Disconnected => RecvTimeoutError::Disconnected,
+            RecvError::Cancelled => RecvTimeoutError::Cancelled,
+        }
+    }
+}
+
+/// recv_cancellable 失败的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvCancellableError {
+    /// CancelToken::cancel 在消息到达之前被调用.
+    Cancelled,
+    /// 与 recv 相同的失败: Sender 断开或调用了 cancel.
+    Recv(RecvError),
+}
+
+impl fmt::Display for RecvCancellableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvCancellableError::Cancelled => "receive aborted by cancel token".fmt(f),
+            RecvCancellableError::Recv(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for RecvCancellableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RecvCancellableError::Cancelled => None,
+            RecvCancellableError::Recv(err) => err.source(),
+        }
+    }
+}
+
+impl From<RecvError> for RecvCancellableError {
+    fn from(err: RecvError) -> Self {
+        RecvCancellableError::Recv(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(SendError(1).to_string(), "sending on a closed channel");
+        assert_eq!(
+            SendTimeoutError::Timeout(1).to_string(),
+            "timed out waiting on send operation"
+        );
+        assert_eq!(
+            format!("{:?}", SendTimeoutError::Disconnected(1)),
+            "Disconnected(..)"
+        );
+        assert_eq!(
+            RecvError::Disconnected.to_string(),
+            "receiving on a closed channel"
+        );
+        assert_eq!(
+            TryRecvError::Empty.to_string(),
+            "receiving on an empty channel"
+        );
+        assert_eq!(
+            TryRecvError::Disconnected.to_string(),
+            "receiving on an empty and disconnected channel"
+        );
+        assert_eq!(
+            RecvTimeoutError::Timeout.to_string(),
+            "timed out waiting on channel"
+        );
+        assert_eq!(
+            RecvTimeoutError::Disconnected.to_string(),
+            "channel is empty and sending half is closed"
+        );
+        assert_eq!(
+            RecvError::Cancelled.to_string(),
+            "receiving on a cancelled channel"
+        );
+        assert_eq!(
+            RecvCancellableError::Cancelled.to_string(),
+            "receive aborted by cancel token"
+        );
+        assert_eq!(
+            RecvCancellableError::from(RecvError::Disconnected).to_string(),
+            "receiving on a closed channel"
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(SendError("msg").into_inner(), "msg");
+        assert_eq!(format!("{:?}", SendError("msg")), "SendError { .. }");
+        assert_eq!(
+            TryRecvError::from(RecvError::Disconnected),
+            TryRecvError::Disconnected
+        );
+        assert_eq!(
+            RecvTimeoutError::from(RecvError::Disconnected),
+            RecvTimeoutError::Disconnected
+        );
+        assert_eq!(
+            TryRecvError::from(RecvError::Cancelled),
+            TryRecvError::Cancelled
+        );
+        assert_eq!(
+            RecvTimeoutError::from(RecvError::Cancelled),
+            RecvTimeoutError::Cancelled
+        );
+
+        let err: Box<dyn Error + Send + Sync> = Box::new(SendError(1));
+        assert_eq!(err.to_string(), "sending on a closed channel");
+    }
+
+    #[test]
+    fn question_mark() {
+        // 每种错误都能通过 ? 转换为 Box<dyn Error>，send 一侧交还的消息可以先取出来.
+        fn fail(step: u8) -> Result<(), Box<dyn Error + Send + Sync>> {
+            match step {
+                0 => Err(SendError(1))?,
+                1 => Err(TrySendError::Full(1))?,
+                2 => Err(SendTimeoutError::Timeout(1))?,
+                3 => Err(RecvError::Disconnected)?,
+                4 => Err(TryRecvError::Empty)?,
+                _ => Err(RecvTimeoutError::Timeout)?,
+            }
+        }
+        let messages: Vec<_> = (0..6)
+            .map(|step| fail(step).unwrap_err().to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "sending on a closed channel",
+                "sending on a full channel",
+                "timed out waiting on send operation",
+                "receiving on a closed channel",
+                "receiving on an empty channel",
+                "timed out waiting on channel",
+            ]
+        );
+        assert_eq!(TrySendError::Disconnected("msg").into_inner(), "msg");
+        assert_eq!(SendTimeoutError::Timeout("msg").into_inner(), "msg");
+    }
+
+    #[test]
+    fn source_chain() {
+        let errors: [&(dyn Error + 'static); 7] = [
+            &SendError(1),
+            &TrySendError::Disconnected(1),
+            &SendTimeoutError::Timeout(1),
+            &RecvError::Cancelled,
+            &TryRecvError::Disconnected,
+            &RecvTimeoutError::Timeout,
+            &RecvCancellableError::Recv(RecvError::Disconnected),
+        ];
+        for err in errors {
+            assert!(err.source().is_none(), "{err}");
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! 可选的 tracing 埋点. 开启 tracing feature 时，send 和阻塞接收在一个名为 channel 的 span 中
+//! 发出 trace 级别的事件，span 和事件都带有 channel_id，可以据此把同一个通道的两端对应起来；
+//! 关闭时这些宏展开为空，不产生任何代码.
+
+/// 进入当前通道的 span，直到所在的代码块结束.
+macro_rules! span {
+    ($id:expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("channel", id = $id).entered();
+    };
+}
+
+/// 发出一个 trace 级别的事件，参数与 tracing::trace! 相同.
+macro_rules! event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::trace!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {event, span};
+
+#[cfg(all(test, feature = "tracing", not(loom)))]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::channel;
+
+    /// 记录每个事件的 message 字段和是否带有 waited_us.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(String, bool)>>>);
+
+    struct Fields(String, bool);
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            match field.name() {
+                "message" => self.0 = format!("{value:?}"),
+                "waited_us" => self.1 = true,
+                _ => {}
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new(), false);
+            event.record(&mut fields);
+            self.0.lock().unwrap().push((fields.0, fields.1));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn send_and_parked_recv_emit_events() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let (sender, receiver) = channel();
+            let dispatch = tracing::dispatcher::get_default(|d| d.clone());
+            let handle = thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    thread::sleep(Duration::from_millis(10));
+                    sender.send(1).unwrap();
+                })
+            });
+            assert_eq!(receiver.recv(), Ok(1));
+            handle.join().unwrap();
+        });
+        let events = recorder.0.lock().unwrap();
+        let find = |name: &str| events.iter().find(|(msg, _)| msg == name);
+        assert!(find("recv parking").is_some());
+        assert!(find("send").is_some());
+        assert_eq!(find("recv woke"), Some(&("recv woke".to_string(), true)));
+    }
+}
@@ -1,78 +1,1116 @@
 #![allow(unused)]
 #![allow(dead_code)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::cell::UnsafeCell;
-use std::collections::VecDeque;
-use std::marker::PhantomData;
-use std::mem::MaybeUninit;
-use std::sync::atomic::AtomicBool;
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread::{self, Thread};
+//! 实现一个One-Shot channel
+//! One-shot: 从一个线程向另一个线程准确地发送一条消息
+//! 使用到的工具:
+//!     1.UnsafeCell 用于存储message，
+//!     2.AtomicU8 用于指示其状态(消息是否可以被消费)，状态机见 state 模块.
+//!
+//! # no_std
+//! 默认开启的 std feature 提供阻塞等待(依赖 thread::park)以及 bounded、broadcast、mpmc、mpsc、once、priority、rendezvous、watch 模块，
+//! 其中 once 模块是广播式的 One-Shot channel，在 crate 根部以 broadcast_once 导出，与多条消息的 broadcast 模块区分开，
+//! 默认开启的 async feature 为 Receiver 实现 Future.
+//! 关闭 std feature 后，crate 只依赖 core 和 alloc，仍然可以使用:
+//!     1.Channel::new / split / split_ref / new_shared / channel / reset / take；
+//!     2.Sender::send / send_with / cancel / clone；
+//!     3.Receiver::is_ready / is_cancelled / peek / try_recv / close；
+//!     4.全部错误类型，单线程的 local 模块，bi 模块(没有阻塞的 recv)，以及 spsc 模块的 push / pop.
+//! 此时接收方只能通过 try_recv 轮询，发送方也不会去唤醒任何线程.
+//! CI 会在 thumbv7em-none-eabi 上以 --no-default-features 编译，确认没有引入对 std 的依赖.
+//!
+//! # Miri
+//! 全部测试在 cargo +nightly miri test 下通过，CI 中也会运行，不会读取未初始化的 MaybeUninit，
+//! 消息不会被重复释放或泄漏. 读取或释放 message 之前，一定先观察到(或者通过 CAS 独占了)READY 状态:
+//!     1.recv / try_recv / on_ready 通过 CAS READY -> READING 独占消息，再 assume_init_read；
+//!     2.Channel 的 Drop / reset / take 持有 &mut self，只在状态仍是 READY(消息没被取走)时释放它.
+//! 依赖具体时序(sleep 或很短的超时)的少数测试在 Miri 下会被忽略.
 
-/// 实现一个One-Shot channel
-/// One-shot: 从一个线程向另一个线程准确地发送一条消息
-/// 使用到的工具:
-///     1.UnsafeCell 用于存储message，
-///     2.AtomicBool 用于指示其状态(消息是否可以被消费).
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use core::cell::Cell;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ops::Deref;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+#[cfg(feature = "std")]
+use std::sync::Condvar;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+pub mod bi;
+#[cfg(feature = "std")]
+pub mod bounded;
+#[cfg(feature = "std")]
+pub mod broadcast;
+mod cache_padded;
+#[cfg(feature = "std")]
+mod cancel;
+pub mod error;
+mod instrument;
+pub mod local;
+mod metrics;
+#[cfg(feature = "std")]
+pub mod mpmc;
+#[cfg(feature = "std")]
+pub mod mpsc;
+#[cfg(feature = "std")]
+pub mod once;
+#[cfg(feature = "std")]
+mod pipe;
+#[cfg(feature = "std")]
+pub mod priority;
+#[cfg(feature = "std")]
+pub mod rendezvous;
+#[cfg(feature = "std")]
+mod select;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod spsc;
+mod state;
+mod sync;
+#[cfg(feature = "std")]
+pub mod wait;
+#[cfg(feature = "std")]
+mod waiter;
+#[cfg(feature = "std")]
+pub mod watch;
+
+pub use bi::{bi_channel, BiChannel};
+use cache_padded::CachePadded;
+#[cfg(feature = "std")]
+pub use cancel::CancelToken;
+pub use error::{
+    RecvCancellableError, RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError,
+    TrySendError,
+};
+pub use local::{channel as local_channel, LocalChannel};
+#[cfg(feature = "metrics")]
+pub use metrics::ChannelMetrics;
+#[cfg(feature = "std")]
+pub use once::channel as broadcast_once;
+#[cfg(feature = "std")]
+pub use pipe::{map_pipe, pipe};
+#[cfg(feature = "std")]
+pub use select::{select2, Either};
+use sync::{const_fn, Arc, AtomicBool, AtomicU8, AtomicUsize, UnsafeCell};
+#[cfg(feature = "std")]
+use sync::{
+    thread::{self, Thread},
+    Mutex,
+};
+#[cfg(feature = "std")]
+pub use wait::{Park, Spin, SpinThenPark, WaitStrategy};
+#[cfg(feature = "std")]
+use waiter::Waiter;
 
 /// 为了防止一个函数被多次调用，我们可以让它按值接受一个参数，对于非 Copy 类型，它会消耗该对象。
 /// 一个对象被消耗或移动后，它就从调用者那里消失了，防止它被再次使用。
 /// 通过将调用send 或receive 的能力分别表示为单独的(非 Copy)类型，并在执行操作时使用该对象，我们可以确保每个调用只能发生一次。
 /// 这将我们带到以下接口设计中，其中通道由一对 Sender 和 Receiver 表示。
-
+///
+/// Sender 可以被 clone，多个线程竞争发送同一条消息(例如"第一个结果胜出")，只有第一次 send 会成功.
 pub struct Sender<'a, T> {
-    inner: &'a Channel<T>,
-    // 为了能够取消接收方的停放，发送方需要知道哪个线程要取消停放。
-    // std::thread::Thread 类型表示线程句柄，这正是我们调用 unpark() 所需要的。
-    // 我们将把接收线程的句柄存储在 Sender 对象中。
-    waiting: Thread,
+    inner: ChannelRef<'a, Channel<T>>,
 }
 
 pub struct Receiver<'a, T> {
-    inner: &'a Channel<T>,
-    // 使用特殊的 PhantomData 标记类型将此限制添加到我们的结构中,
-    // 不再允许它在线程之间发送来解决这个问题(在线程之间发送Receiver对象，则句柄waiting将指向错误的线程)
-    _marker: PhantomData<*const ()>,
+    inner: ChannelRef<'a, Channel<T>>,
+    // received : 消息是否已经被 try_recv 取走. try_recv 只借用 &mut self,
+    // 取走消息后 Receiver 仍然存活，需要记录下来以防止 recv 再去等待一条不会再来的消息.
+    received: bool,
+    // peek / recv_ref 通过 &self 交出 &T. 如果 Receiver 是 Sync，多个线程就能同时拿到 &T，
+    // 这要求 T: Sync；而且 waiter 只能登记一个等待方. 所以 Receiver 只是 Send，不是 Sync.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+/// 由 channel() 得到的、不借用任何栈上数据的 Sender.
+pub type OwnedSender<T> = Sender<'static, T>;
+
+/// 由 channel() 得到的、不借用任何栈上数据的 Receiver.
+pub type OwnedReceiver<T> = Receiver<'static, T>;
+
+/// 创建一个放在堆上的 One-Shot channel，等价于 Channel::new_shared().
+/// 两端都是 'static 的，不需要 thread::scope 就可以把 Sender move 进 thread::spawn 的线程.
+pub fn channel<T>() -> (OwnedSender<T>, OwnedReceiver<T>) {
+    Channel::new_shared()
+}
+
+/// 返回一个消息已经就绪的 Receiver，不需要 Sender，见 Channel::with_value.
+pub fn ready<T>(msg: T) -> OwnedReceiver<T> {
+    let (sender, receiver) = Channel::shared(Channel::with_value(msg));
+    // 状态已经是 READY，Sender 的 drop 不会改变它.
+    drop(sender);
+    receiver
+}
+
+/// Sender/Receiver 对通道的引用方式:
+///     1.Borrowed: 由 split() 得到，借用栈上的 Channel，生命周期受限于该借用；
+///     2.Shared: 由 new_shared() 得到，共同持有堆上的 Arc<Channel>，生命周期为 'static.
+/// 两种方式共享同一套 send/recv 实现，通过 Deref 统一成 &Channel<T>.
+/// bounded 等模块中的通道也复用这一类型.
+enum ChannelRef<'a, C> {
+    Borrowed(&'a C),
+    Shared(Arc<C>),
+}
+
+impl<C> Clone for ChannelRef<'_, C> {
+    fn clone(&self) -> Self {
+        match self {
+            ChannelRef::Borrowed(channel) => ChannelRef::Borrowed(channel),
+            ChannelRef::Shared(channel) => ChannelRef::Shared(channel.clone()),
+        }
+    }
+}
+
+impl<C> Deref for ChannelRef<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        match self {
+            ChannelRef::Borrowed(channel) => channel,
+            ChannelRef::Shared(channel) => channel,
+        }
+    }
 }
 
 impl<T> Sender<'_, T> {
-    pub fn send(self, msg: T) {
-        unsafe { (*self.inner.message.get()).write(msg) };
+    /// 发送消息. 如果 Receiver 已经被丢弃，不会把消息写进通道，而是通过 SendError 交还给调用者.
+    /// 注意这只是尽力而为: Receiver 可能在检查之后、被 recv 之前才被丢弃，
+    /// 此时返回 Ok，而消息留在通道中由 Channel 的 Drop 负责释放.
+    ///
+    /// 存在多个 Sender 时，只有第一个 send 能写入消息，其余的同样通过 SendError 拿回自己的消息.
+    /// "第一个写入者胜出"由 state 上 EMPTY -> WRITING 的 compare_exchange 决定: 只有成功的那一个 Sender
+    /// 会去写 message，因此 message 不会被并发写入. 这次 compare_exchange 只需要原子性，使用 Relaxed 即可:
+    /// 消息本身由随后 WRITING -> READY 的 store(Release) 发布给接收方；Receiver 已经被丢弃、
+    /// 已经有 Sender 发送或 cancel 时状态不是 EMPTY，compare_exchange 失败，消息交还给调用者.
+    pub fn send(self, msg: T) -> Result<(), SendError<T>> {
+        self.send_with(|| msg)
+            .map_err(|SendError(f)| SendError(f()))
+    }
+
+    /// 与 send 相同，但消息由 f 生成，f 的返回值直接写入通道的存储，
+    /// 对于很大的 T 可以省去先在调用方栈上构造再拷贝进通道的开销.
+    /// 无法发送时(Receiver 已被丢弃，或另一个 Sender 已经胜出)不会调用 f，而是把 f 交还给调用者.
+    ///
+    /// 唤醒只在接收方已经登记(准备停放或已经停放)时发生: 接收方在停放之前设置 Waiter 的 parked 标志，
+    /// 发送方先写状态再检查这个标志，两边之间各有一次 SeqCst fence，所以即将停放的接收方不会错过唤醒，
+    /// 而轮询 try_recv 或者稍后才来接收的一方不会让 send 付出加锁和 unpark 的代价(见 benches/send_latency.rs).
+    ///
+    /// 只有 f 成功返回、消息完整写入之后才会进入 READY 并唤醒接收方. 如果 f panic，
+    /// message 从未被写入，这里会把状态从 WRITING 恢复为 EMPTY: 其他 Sender 仍然可以发送，
+    /// 若这是最后一个 Sender，接收方会收到 Disconnected，既不会泄漏也不会重复释放.
+    /// write 与 store(READY) 之间没有任何可能 panic 的代码(移动 T 只是内存拷贝)，所以不会出现
+    /// 消息已经写入、状态却不是 READY 的情况. 之后唯一可能 panic 的是 unpark 中调用的、
+    /// 由用户提供的 Waker: 此时消息已经处于 READY，仍然可以被接收，或者由 Channel 的 Drop 释放.
+    pub fn send_with<F: FnOnce() -> T>(self, f: F) -> Result<(), SendError<F>> {
+        if self
+            .inner
+            .state
+            .compare_exchange(
+                state::EMPTY,
+                state::WRITING,
+                core::sync::atomic::Ordering::Relaxed,
+                core::sync::atomic::Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return Err(SendError(f));
+        }
+
+        // f panic 时回到 EMPTY. guard 先于 self 被释放，
+        // 所以 Sender 的 Drop 看到的已经是恢复之后的状态.
+        struct Release<'c>(&'c AtomicU8);
+        impl Drop for Release<'_> {
+            fn drop(&mut self) {
+                self.0
+                    .store(state::EMPTY, core::sync::atomic::Ordering::Relaxed);
+            }
+        }
+        let guard = Release(&self.inner.state);
+        let msg = f();
+        mem::forget(guard);
+
+        self.publish(msg);
+        Ok(())
+    }
+
+    /// 与 send 相同的"第一个写入者胜出"，但只借用 &self: 多个竞争的生产者各自持有一个 clone，
+    /// 每个都可以尝试一次，失败的一方通过 TrySendError 立即拿回自己的消息，从不阻塞:
+    ///     1.Full: 另一个 Sender 已经胜出(消息正在写入、已经就绪或已经被接收)；
+    ///     2.Disconnected: Receiver 已经被丢弃，或者某个 Sender 调用了 cancel，不会再有人接收.
+    /// 内存序与 send 相同: EMPTY -> WRITING 的 compare_exchange 只需要原子性，成功和失败都用 Relaxed，
+    /// 消息由随后 WRITING -> READY 的 store(Release) 发布给用 Acquire 读取状态的接收方；
+    /// 失败时只返回调用者自己的消息，不读取通道中的任何数据，所以也不需要 Acquire.
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        match self.inner.state.compare_exchange(
+            state::EMPTY,
+            state::WRITING,
+            core::sync::atomic::Ordering::Relaxed,
+            core::sync::atomic::Ordering::Relaxed,
+        ) {
+            Ok(_) => {}
+            Err(state::CLOSED_BY_RECEIVER | state::CANCELLED) => {
+                return Err(TrySendError::Disconnected(msg))
+            }
+            Err(_) => return Err(TrySendError::Full(msg)),
+        }
+        self.publish(msg);
+        Ok(())
+    }
+
+    /// 赢得 WRITING 之后写入消息，进入 READY 并唤醒接收方.
+    fn publish(&self, msg: T) {
+        self.inner
+            .message
+            .with_mut(|ptr| unsafe { (*ptr).write(msg) });
         self.inner
-            .ready
-            .store(true, std::sync::atomic::Ordering::Release);
-        self.waiting.unpark();
+            .state
+            .store(state::READY, core::sync::atomic::Ordering::Release);
+        metrics::record!(self.inner, sends);
+        instrument::span!(self.channel_id());
+        instrument::event!("send");
+        #[cfg(feature = "std")]
+        self.inner.notify();
+    }
+
+    /// 与 send 不同，可以在接收方取走之前反复发送，新消息覆盖还没被读取的旧消息(类似 watch channel):
+    /// 通道为空时返回 Ok(None)，覆盖了一条已经发送但还没被接收的消息时返回 Ok(Some(旧消息))，
+    /// 旧消息的所有权交还给调用者，不会泄漏. 消息已经被取走、Sender 调用了 cancel，
+    /// 或者 Receiver 在发送之前就已经被丢弃时返回 Err，新消息通过 SendError 交还.
+    ///
+    /// 覆盖是 READY -> WRITING -> READY: 与接收方的 READY -> READING 竞争同一次 compare_exchange，
+    /// 所以旧消息要么被接收方取走，要么交还给这里的调用者，不会两边都拿到.
+    /// 另一个 Sender 正在写入或接收方正在读取时，短暂自旋等待这个中间状态结束.
+    ///
+    /// 接收方通过 peek / recv_ref 借出过对消息的引用之后，旧消息可能仍被引用，不能再移走它:
+    /// 此时同样返回 Err，旧消息留在通道中. 借出的一方先置位 lent 再检查 READY，这里先赢得
+    /// READY -> WRITING 再检查 lent，两边都用 SeqCst，所以至少有一方会看到对方.
+    pub fn send_replace(&self, msg: T) -> Result<Option<T>, SendError<T>> {
+        let old = loop {
+            match self.inner.state.compare_exchange(
+                state::EMPTY,
+                state::WRITING,
+                core::sync::atomic::Ordering::Relaxed,
+                core::sync::atomic::Ordering::Relaxed,
+            ) {
+                Ok(_) => break None,
+                Err(state::READY) => {
+                    // Acquire: 与写入旧消息的 store(READY, Release) 配对，才能读出完整的旧消息.
+                    if self
+                        .inner
+                        .state
+                        .compare_exchange(
+                            state::READY,
+                            state::WRITING,
+                            core::sync::atomic::Ordering::SeqCst,
+                            core::sync::atomic::Ordering::Relaxed,
+                        )
+                        .is_ok()
+                    {
+                        if self.inner.lent.load(core::sync::atomic::Ordering::SeqCst) {
+                            // 没有动过 message，直接回到 READY.
+                            self.inner
+                                .state
+                                .store(state::READY, core::sync::atomic::Ordering::Release);
+                            return Err(SendError(msg));
+                        }
+                        break Some(
+                            self.inner
+                                .message
+                                .with(|ptr| unsafe { (*ptr).assume_init_read() }),
+                        );
+                    }
+                }
+                Err(state::WRITING | state::READING) => core::hint::spin_loop(),
+                Err(_) => return Err(SendError(msg)),
+            }
+        };
+        self.publish(msg);
+        Ok(old)
+    }
+
+    /// 是否与 rx 属于同一个通道. 比较的是底层 Channel 的地址，split() 借用的栈上通道和
+    /// channel() 创建的堆上通道都适用(后者等价于 Arc::ptr_eq).
+    pub fn same_channel(&self, rx: &Receiver<'_, T>) -> bool {
+        self.channel_id() == rx.channel_id()
+    }
+
+    /// 底层 Channel 的地址，可以作为 map 的 key 把 Sender 和 Receiver 配对.
+    /// 只在通道存活期间唯一: 通道被释放后，它的地址可能被新的通道复用.
+    pub fn channel_id(&self) -> usize {
+        &*self.inner as *const Channel<T> as usize
+    }
+
+    /// Receiver 是否还没有被丢弃(或 close). 返回 false 时 send 一定会失败，
+    /// 调用方可以据此省去构造一条昂贵的消息. 不需要额外的标志: Receiver 的 Drop 把 EMPTY 变为
+    /// CLOSED_BY_RECEIVER，send 本来就因为这个状态而失败.
+    /// 返回 true 只是一个快照，Receiver 可能随后就被丢弃，此时 send 仍然会交还消息.
+    /// 消息已经发送之后 Receiver 被丢弃不会改变状态(消息留在通道中)，这里仍然返回 true.
+    pub fn is_receiver_alive(&self) -> bool {
+        self.inner.state() != state::CLOSED_BY_RECEIVER
+    }
+
+    /// 见 Channel::metrics. channel() 创建的通道放在堆上，只能通过任意一端取得它的统计.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Arc<ChannelMetrics> {
+        self.inner.metrics()
+    }
+
+    /// 明确表示不会再有消息: 接收方的 recv 返回 RecvError::Cancelled，与 Sender 被意外丢弃时的
+    /// Disconnected 区分开. 与 send 一样从 EMPTY 出发(EMPTY -> CANCELLED)，所以 cancel 之后其他 Sender 的
+    /// send 会失败，已经有 Sender 发送了消息时 cancel 什么也不做，消息仍然会被接收.
+    pub fn cancel(self) {
+        if self
+            .inner
+            .state
+            .compare_exchange(
+                state::EMPTY,
+                state::CANCELLED,
+                core::sync::atomic::Ordering::Release,
+                core::sync::atomic::Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            #[cfg(feature = "std")]
+            self.inner.waiter.unpark();
+        }
     }
 }
 
-impl<T> Receiver<'_, T> {
-    pub fn is_ready(&self) -> bool {
-        self.inner.ready.load(std::sync::atomic::Ordering::Relaxed)
+/// into_raw / from_raw 用于把通道的一端交给 C 代码(例如作为回调的 void *user_data)，稍后再取回.
+/// 指针就是 Arc::into_raw 得到的堆上 Channel 的地址，对 C 代码是不透明的.
+/// 所有权规则:
+///     1.into_raw 之后这一端仍然存活(没有被 drop)，在 from_raw 取回之前，对端看不到它被丢弃；
+///     2.每次 into_raw 必须恰好对应一次 from_raw，用同样的 T: 从未取回会泄漏通道(以及其中的消息)，
+///       取回两次会重复释放；
+///     3.Sender 的指针只能用 Sender::from_raw 取回，Receiver 的只能用 Receiver::from_raw 取回.
+impl<T> OwnedSender<T> {
+    /// # Panics
+    /// 只适用于 channel() / new_shared() 得到的堆上通道，split_ref 借用静态 Channel 得到的 Sender 会 panic.
+    pub fn into_raw(self) -> *mut () {
+        let this = mem::ManuallyDrop::new(self);
+        match &this.inner {
+            ChannelRef::Shared(channel) => {
+                Arc::into_raw(unsafe { core::ptr::read(channel) }) as *mut ()
+            }
+            ChannelRef::Borrowed(_) => panic!("into_raw requires a heap-allocated channel"),
+        }
+    }
+
+    /// # Safety
+    /// ptr 必须来自 Sender::<T>::into_raw，并且还没有被 from_raw 取回过.
+    pub unsafe fn from_raw(ptr: *mut ()) -> Self {
+        Sender {
+            inner: ChannelRef::Shared(Arc::from_raw(ptr as *const Channel<T>)),
+        }
+    }
+}
+
+impl<T: Send> Clone for Sender<'_, T> {
+    fn clone(&self) -> Self {
+        // 与 Arc 相同，新增引用时只需要 Relaxed: 能 clone 说明计数至少为 1，不会与归零竞争.
+        self.inner
+            .senders
+            .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        Sender {
+            inner: self.inner.clone(),
+        }
     }
+}
 
-    pub fn recv(self) -> T {
-        while !self
+impl<T> Drop for Sender<'_, T> {
+    /// 最后一个 Sender 被丢弃时，如果没有任何 Sender 发送过消息(状态仍是 EMPTY)，进入 CLOSED_BY_SENDER
+    /// 并唤醒接收方，否则接收方会永远停放. 这是对同一个 state 的 compare_exchange，
+    /// 已经发送或 cancel 的通道不是 EMPTY，已发送的消息永远不会被报告为 Disconnected.
+    fn drop(&mut self) {
+        if self
             .inner
-            .ready
-            .swap(false, std::sync::atomic::Ordering::Acquire)
+            .senders
+            .fetch_sub(1, core::sync::atomic::Ordering::AcqRel)
+            != 1
         {
-            thread::park();
+            return;
+        }
+        if self
+            .inner
+            .state
+            .compare_exchange(
+                state::EMPTY,
+                state::CLOSED_BY_SENDER,
+                core::sync::atomic::Ordering::Release,
+                core::sync::atomic::Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            #[cfg(feature = "std")]
+            self.inner.waiter.unpark();
+        }
+    }
+}
+
+/// receiver_alive : Receiver 是否还没有被丢弃(或 close)；waiter 中是 Receiver 登记的等待方.
+impl<T> fmt::Debug for Sender<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.state.load(core::sync::atomic::Ordering::Relaxed);
+        let mut d = f.debug_struct("Sender");
+        d.field("ready", &(state == state::READY))
+            .field("receiver_alive", &self.is_receiver_alive());
+        #[cfg(feature = "std")]
+        d.field("waiter", &self.inner.waiter);
+        d.finish()
+    }
+}
+
+/// senders_alive : 是否还有 Sender 存活. 为 false 且消息没有到达时，recv 会返回 Disconnected.
+impl<T> fmt::Debug for Receiver<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Receiver");
+        d.field(
+            "ready",
+            &(self.inner.state.load(core::sync::atomic::Ordering::Relaxed) == state::READY),
+        )
+        .field("received", &self.received)
+        .field(
+            "senders_alive",
+            &(self
+                .inner
+                .senders
+                .load(core::sync::atomic::Ordering::Relaxed)
+                != 0),
+        );
+        #[cfg(feature = "std")]
+        d.field("waiter", &self.inner.waiter);
+        d.finish()
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    /// EMPTY -> CLOSED_BY_RECEIVER，让之后的 send 把消息交还给调用者，而不是写进一个没人读取的通道.
+    /// 消息已经在写入或已经就绪时状态保持不变，消息留在通道中.
+    fn drop(&mut self) {
+        let _ = self.inner.state.compare_exchange(
+            state::EMPTY,
+            state::CLOSED_BY_RECEIVER,
+            core::sync::atomic::Ordering::Relaxed,
+            core::sync::atomic::Ordering::Relaxed,
+        );
+    }
+}
+
+impl<'a, T> Receiver<'a, T> {
+    /// 消息是否已经到达. 返回 true 之后，调用方可以认为消息已经完整写入通道.
+    pub fn is_ready(&self) -> bool {
+        // 不要改回 Relaxed: 调用方常常在 is_ready 返回 true 之后直接读取消息(例如 peek)，
+        // 若这里没有与 send 中 store(READY, Release) 配对的 Acquire，就无法保证看到的是
+        // 完整写入的 MaybeUninit，而不是写入之前的内容. 对状态的读取本来就很少在热路径上，
+        // Acquire 在 x86 上没有额外开销，在弱内存序平台上也只是一次普通的有序读取.
+        self.inner.state() == state::READY
+    }
+
+    /// 是否与 tx 属于同一个通道，见 Sender::same_channel.
+    pub fn same_channel(&self, tx: &Sender<'_, T>) -> bool {
+        tx.same_channel(self)
+    }
+
+    /// 底层 Channel 的地址，与同一通道的 Sender::channel_id 相等.
+    pub fn channel_id(&self) -> usize {
+        &*self.inner as *const Channel<T> as usize
+    }
+
+    /// 见 Channel::metrics.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Arc<ChannelMetrics> {
+        self.inner.metrics()
+    }
+
+    /// Sender 是否调用了 cancel. 为 true 时消息永远不会到达，轮询的调用方可以据此提前放弃.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.state() == state::CANCELLED
+    }
+
+    /// 表示不再需要这条消息: 之后的 send 会通过 SendError 把消息交还给调用者，
+    /// 而不是写进一个没人读取的通道. 与直接 drop Receiver 效果相同，只是意图更明确.
+    /// 如果消息已经到达，会在这里立即释放，而不是等到 Channel 本身被释放.
+    pub fn close(mut self) {
+        drop(self.try_recv());
+    }
+
+    /// 在不取走消息的情况下查看它: 消息已经到达时返回对它的共享引用，ready 保持不变，
+    /// 之后的 recv / try_recv 仍然能完整地取得这条消息，且只取得一次.
+    ///
+    /// 安全性: is_ready 用 Acquire 读取状态，与 send 中的 Release 配对，才能保证看到完整写入的消息.
+    /// 看到 READY 之后不会再有并发的写入: 只有赢得 WRITING 的那一个 Sender 会写 message，
+    /// 而且写在进入 READY 之前.
+    /// 能取走消息的只有 Receiver 自己，而返回的引用借用了 &self，在它存活期间无法调用
+    /// 需要 self 或 &mut self 的 recv / try_recv，所以消息不会在被引用时被移走.
+    /// 另一个能移走消息的是 Sender::send_replace: 借出引用之前先置位 lent，之后它总是返回 Err.
+    pub fn peek(&self) -> Option<&T> {
+        if !self.is_ready() {
+            return None;
+        }
+        self.inner
+            .lent
+            .store(true, core::sync::atomic::Ordering::SeqCst);
+        // 置位之前赢得 READY -> WRITING 的 send_replace 可能没有看到 lent，等它写完新消息.
+        // 只有 send_replace 会从 READY 进入 WRITING，它不会阻塞，这里只需短暂自旋.
+        loop {
+            match self.inner.state.load(core::sync::atomic::Ordering::SeqCst) {
+                state::READY => break,
+                state::WRITING => core::hint::spin_loop(),
+                _ => return None,
+            }
+        }
+        Some(
+            self.inner
+                .message
+                .with(|ptr| unsafe { (*ptr).assume_init_ref() }),
+        )
+    }
+
+    /// 非阻塞地尝试接收消息，适合在事件循环中轮询.
+    /// 与 recv 不同，它只借用 &mut self: 消息未到达时返回 Empty，Receiver 仍可继续使用.
+    /// 对状态只做一次 compare_exchange(READY, READING): 消息未就绪时不会写入任何值，
+    /// 并发到达的消息不会因为这次检查而丢失，轮询也不会反复弄脏状态所在的缓存行；
+    /// 就绪时恰好由这一次 compare_exchange 取得消息的所有权，失败时返回的当前状态说明了原因.
+    /// 取得消息后再次调用只会返回 Empty，不会重复读取 MaybeUninit；只关心有没有消息时可以用 try_recv().ok().
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if self.received {
+            return Err(TryRecvError::Empty);
+        }
+        match self.inner.state.compare_exchange(
+            state::READY,
+            state::READING,
+            core::sync::atomic::Ordering::Acquire,
+            core::sync::atomic::Ordering::Acquire,
+        ) {
+            Ok(_) => {}
+            Err(state::CANCELLED) => return Err(TryRecvError::Cancelled),
+            Err(state::CLOSED_BY_SENDER) => return Err(TryRecvError::Disconnected),
+            Err(_) => return Err(TryRecvError::Empty),
+        }
+        self.received = true;
+        let msg = self
+            .inner
+            .message
+            .with(|ptr| unsafe { (*ptr).assume_init_read() });
+        // 只有 Receiver 自己会离开 READING，这里不需要再与其他线程同步.
+        self.inner
+            .state
+            .store(state::RECEIVED, core::sync::atomic::Ordering::Relaxed);
+        metrics::record!(self.inner, recvs);
+        Ok(msg)
+    }
+
+    /// 在接收的位置直接变换消息，例如解析或包装，不需要额外的中间变量.
+    /// 得到的 MappedReceiver 提供与 Receiver 相同的 try_recv / recv / recv_timeout / recv_deadline，
+    /// 断开、cancel 与超时的行为不变，只是成功时返回 f(msg). f 只会被调用一次.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> MappedReceiver<'a, T, U, F> {
+        MappedReceiver {
+            inner: self,
+            f: Some(f),
+            _output: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Receiver<'_, T> {
+    /// 阻塞直到消息到达. 如果 Sender 未发送就被丢弃，返回 RecvError::Disconnected 而不是永远停放.
+    /// READY 与 CLOSED_BY_SENDER 是不同的状态，不会同时成立，已发送的消息不会被误报为断开.
+    ///
+    /// # Panics
+    /// 如果消息已经通过 try_recv 取走，再调用 recv 将永远等不到消息，此时直接 panic.
+    pub fn recv(mut self) -> Result<T, RecvError> {
+        self.wait(None).map_err(|err| match err {
+            RecvTimeoutError::Cancelled => RecvError::Cancelled,
+            _ => RecvError::Disconnected,
+        })
+    }
+
+    /// 最多阻塞 timeout 时长. 超时后消息仍留在通道中(Channel 的 Drop 会负责释放它)，
+    /// 由于只借用 &mut self，超时后可以用同一个 Receiver 重试.
+    /// 只关心有没有在时限内收到消息时，可以用 recv_timeout(..).ok() 得到 Option<T>.
+    ///
+    /// # Panics
+    /// 与 recv 相同，消息已经被取走后调用会 panic.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        // 时长过大导致 Instant 溢出时，视为没有截止时间.
+        self.wait(Instant::now().checked_add(timeout))
+    }
+
+    /// 阻塞直到消息到达或到达截止时间 deadline，其余语义与 recv_timeout 相同.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.wait(Some(deadline))
+    }
+
+    /// 阻塞直到消息到达，返回对通道中消息的引用，而不是把它移出来.
+    /// 与 peek 相同，ready 保持不变，消息仍由 Channel 的 Drop(或之后的 recv / try_recv)负责释放，
+    /// 可以多次调用，每次都得到同一条消息. 返回的引用借用了 &self，在它存活期间无法调用
+    /// 需要 self 或 &mut self 的 recv / try_recv，所以不会出现消息被移走之后仍被引用，或者被读取两次.
+    ///
+    /// # Panics
+    /// 与 recv 相同，消息已经被取走后调用会 panic.
+    pub fn recv_ref(&self) -> Result<&T, RecvError> {
+        assert!(!self.received, "message already received by try_recv");
+        match self.wait_until_ready(None) {
+            Ok(()) => Ok(self.peek().expect("ready message")),
+            Err(RecvTimeoutError::Cancelled) => Err(RecvError::Cancelled),
+            Err(_) => Err(RecvError::Disconnected),
+        }
+    }
+
+    /// 等待消息到达但不取走它: 最多阻塞 timeout 时长(None 表示不限时)，返回是否观察到了 ready.
+    /// 返回 true 之后，同一个 Receiver 上的 recv / try_recv 会立即拿到消息，不会再次 park，
+    /// 因此可以在这一层等待，把 Receiver 交给另一层去消费. Sender 断开或 cancel 时立即返回 false.
+    ///
+    /// 等待方在这里登记的是调用 wait_ready 的线程，所以被唤醒的一定是正在等待的这个线程，
+    /// 之后在哪个线程上调用 recv 都可以.
+    pub fn wait_ready(&self, timeout: Option<Duration>) -> bool {
+        if self.received {
+            return false;
+        }
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        self.wait_until_ready(deadline).is_ok()
+    }
+
+    /// 回调形式的接收，便于接入自定义的 reactor: 消息到达时由 send 在发送方的线程上调用 f(msg)，
+    /// 消息已经到达时立即在当前线程上调用. f 只会被调用一次，并且与 Future 一样不需要阻塞任何线程.
+    /// 登记沿用 waiter 的协议: 先登记回调再检查一次状态，send 先写状态再检查登记，
+    /// 两边都看到对方时由谁先从通道中取走回调决定由谁调用，所以既不会错过也不会调用两次.
+    ///
+    /// 回调保存在通道中，所以必须是 'static 的. Sender 没有发送就被丢弃或调用了 cancel 时
+    /// f 不会被调用，而是随通道(或下一次 split / reset)一起被释放；
+    /// 消息已经被 try_recv 取走时 f 被立即丢弃. f 在 send 中被调用时，它的 panic 会从 send 传出，
+    /// 此时消息已经交给 f，通道不会再次释放它.
+    pub fn on_ready<F: FnOnce(T) + Send + 'static>(self, f: F) {
+        if self.received {
+            return;
+        }
+        *self.inner.callback.lock().unwrap() = Some(Box::new(f));
+        self.inner.waiter.register_callback();
+        if self.inner.state() == state::READY {
+            self.inner.run_callback();
+        }
+        // 回调代替 Receiver 接收消息，不需要它的 Drop 把通道标记为 CLOSED_BY_RECEIVER.
+        let this = mem::ManuallyDrop::new(self);
+        drop(unsafe { core::ptr::read(&this.inner) });
+    }
+
+    /// recv_ref 与 wait_ready 共用的等待循环，与 wait 相同(包括等待策略)，但只观察状态(Acquire)，不取走消息.
+    fn wait_until_ready(&self, deadline: Option<Instant>) -> Result<(), RecvTimeoutError> {
+        let check = || match self.inner.state() {
+            state::READY => Some(Ok(())),
+            state::CANCELLED => Some(Err(RecvTimeoutError::Cancelled)),
+            state::CLOSED_BY_SENDER => Some(Err(RecvTimeoutError::Disconnected)),
+            _ => None,
+        };
+        let mut attempt = 0;
+        loop {
+            if let Some(result) = check() {
+                return result;
+            }
+            if !self.inner.strategy.spin(attempt) {
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            attempt = attempt.saturating_add(1);
         }
-        unsafe { (*self.inner.message.get()).assume_init_read() }
+        self.inner.waiter.register();
+        let result = loop {
+            if let Some(result) = check() {
+                break result;
+            }
+            match deadline {
+                None => {
+                    metrics::record!(self.inner, parks);
+                    thread::park();
+                }
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break Err(RecvTimeoutError::Timeout);
+                    }
+                    metrics::record!(self.inner, parks);
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        };
+        self.inner.waiter.unregister();
+        result
+    }
+
+    /// recv / recv_timeout / recv_deadline 共用的等待循环.
+    /// 消息已经到达时直接返回，不登记线程句柄；否则先把当前线程登记到 Channel 的 waiter 中，
+    /// 再检查一次 ready 之后才 park，这样在登记和 park 之间到达的消息也会唤醒我们.
+    /// park 和 park_timeout 都可能被虚假唤醒，所以每次醒来都重新检查 ready，
+    /// 并根据 deadline 重新计算剩余时间，虚假唤醒不会缩短等待.
+    /// 在停放之前先按通道的等待策略自旋，见 wait 模块.
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        assert!(!self.received, "message already received by try_recv");
+        let mut attempt = 0;
+        loop {
+            if let Some(result) = self.check() {
+                return result;
+            }
+            if !self.inner.strategy.spin(attempt) {
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            attempt = attempt.saturating_add(1);
+        }
+        self.inner.waiter.register();
+        instrument::span!(self.channel_id());
+        instrument::event!("recv parking");
+        #[cfg(feature = "tracing")]
+        let parked_at = Instant::now();
+        let result = loop {
+            if let Some(result) = self.check() {
+                break result;
+            }
+            match deadline {
+                None => {
+                    metrics::record!(self.inner, parks);
+                    thread::park();
+                }
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break Err(RecvTimeoutError::Timeout);
+                    }
+                    metrics::record!(self.inner, parks);
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        };
+        self.inner.waiter.unregister();
+        instrument::event!(
+            waited_us = parked_at.elapsed().as_micros() as u64,
+            ok = result.is_ok(),
+            "recv woke"
+        );
+        result
+    }
+
+    /// 非阻塞地检查一次，消息还没到达时返回 None.
+    fn check(&mut self) -> Option<Result<T, RecvTimeoutError>> {
+        match self.try_recv() {
+            Ok(msg) => Some(Ok(msg)),
+            Err(TryRecvError::Disconnected) => Some(Err(RecvTimeoutError::Disconnected)),
+            Err(TryRecvError::Cancelled) => Some(Err(RecvTimeoutError::Cancelled)),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+/// 在异步代码中可以直接 .await 一个 Receiver，而不需要用 thread::park 阻塞执行器线程.
+/// poll 时先检查 ready，未就绪则把当前任务的 Waker 登记在 Channel 的 waiter 中，
+/// 登记之后再检查一次 ready: 与阻塞等待相同，这样在登记前后到达的消息都不会错过唤醒.
+/// Future 完成之后不应再被 poll.
+/// Receiver 本身就是这个 Future(同时通过标准库的 blanket impl 实现了 IntoFuture)，
+/// 所以不需要额外的 RecvFuture 包装，也不依赖任何特定的异步运行时，只用到了 std::task.
+/// 由默认开启的 async feature 提供.
+#[cfg(feature = "async")]
+impl<T> Future for Receiver<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+/// Future 之下的底层轮询接口，供自定义的执行器和 select 之类的组合子使用，不需要 Box 或 Pin.
+#[cfg(feature = "async")]
+impl<T> Receiver<'_, T> {
+    /// 消息到达时返回 Poll::Ready(Ok(msg))，否则把 cx 的 Waker 登记在通道中并返回 Poll::Pending，
+    /// Receiver 不会被消耗. 每次 poll 都会重新登记: 与上一次的 Waker 不是同一个任务(will_wake 为 false)时
+    /// 替换它. 登记之后再检查一次状态，在检查和登记之间到达的消息不会错过唤醒.
+    ///
+    /// # Panics
+    /// 返回过 Poll::Ready(Ok(_)) 之后(或者消息已经通过 try_recv 取走)再调用会 panic.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        assert!(!self.received, "message already received by try_recv");
+        fn check<T>(rx: &mut Receiver<'_, T>) -> Option<Result<T, RecvError>> {
+            match rx.try_recv() {
+                Ok(msg) => Some(Ok(msg)),
+                Err(TryRecvError::Disconnected) => Some(Err(RecvError::Disconnected)),
+                Err(TryRecvError::Cancelled) => Some(Err(RecvError::Cancelled)),
+                Err(TryRecvError::Empty) => None,
+            }
+        }
+        if let Some(result) = check(self) {
+            return Poll::Ready(result);
+        }
+        self.inner.waiter.register_waker(cx.waker());
+        let Some(result) = check(self) else {
+            return Poll::Pending;
+        };
+        self.inner.waiter.unregister();
+        Poll::Ready(result)
+    }
+
+    /// 与 poll_recv 相同地等待，但只观察状态，不取走消息: 返回 Poll::Ready(Ok(())) 之后，
+    /// 下一次 try_recv / poll_recv 一定会拿到消息. 可以对同一个 Receiver 反复调用，
+    /// 例如 select 在多个通道上轮询，只从第一个就绪的通道中取走消息.
+    /// 消息已经被取走时返回 Poll::Ready(Err(RecvError::Disconnected)).
+    pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), RecvError>> {
+        if self.received {
+            return Poll::Ready(Err(RecvError::Disconnected));
+        }
+        let check = || match self.inner.state() {
+            state::READY => Some(Ok(())),
+            state::CANCELLED => Some(Err(RecvError::Cancelled)),
+            state::CLOSED_BY_SENDER => Some(Err(RecvError::Disconnected)),
+            _ => None,
+        };
+        if let Some(result) = check() {
+            return Poll::Ready(result);
+        }
+        self.inner.waiter.register_waker(cx.waker());
+        let Some(result) = check() else {
+            return Poll::Pending;
+        };
+        self.inner.waiter.unregister();
+        Poll::Ready(result)
+    }
+}
+
+/// 把 Receiver 当作最多只有一个元素的迭代器: 第一次 next 阻塞直到消息到达并返回它，之后总是 None.
+/// Sender 没有发送就被丢弃或者调用了 cancel 时，第一次 next 也立即返回 None，不会永远停放；
+/// 消息已经通过 try_recv 取走时同样只得到 None.
+#[cfg(feature = "std")]
+impl<'a, T> IntoIterator for Receiver<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> IntoIter<'a, T> {
+        IntoIter {
+            receiver: (!self.received).then_some(self),
+        }
+    }
+}
+
+/// Receiver::into_iter 返回的迭代器.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IntoIter<'a, T> {
+    // receiver : 第一次 next 时被取走.
+    receiver: Option<Receiver<'a, T>>,
+}
+
+#[cfg(feature = "std")]
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.take()?.recv().ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.receiver.is_some() as usize))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> core::iter::FusedIterator for IntoIter<'_, T> {}
+
+impl<T> OwnedReceiver<T> {
+    /// 见 OwnedSender::into_raw.
+    ///
+    /// # Panics
+    /// 与 OwnedSender::into_raw 相同，split_ref 得到的 Receiver 会 panic.
+    pub fn into_raw(self) -> *mut () {
+        let this = mem::ManuallyDrop::new(self);
+        match &this.inner {
+            ChannelRef::Shared(channel) => {
+                Arc::into_raw(unsafe { core::ptr::read(channel) }) as *mut ()
+            }
+            ChannelRef::Borrowed(_) => panic!("into_raw requires a heap-allocated channel"),
+        }
+    }
+
+    /// # Safety
+    /// ptr 必须来自 Receiver::<T>::into_raw，并且还没有被 from_raw 取回过.
+    pub unsafe fn from_raw(ptr: *mut ()) -> Self {
+        let channel = Arc::from_raw(ptr as *const Channel<T>);
+        // received 不在通道里，但取走消息之后通道停在 RECEIVED，可以据此恢复.
+        let received = channel.state() == state::RECEIVED;
+        Receiver {
+            inner: ChannelRef::Shared(channel),
+            received,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 一次交换完成后重新武装同一块堆上存储，得到一对新的 Sender/Receiver，省去 channel() 的分配.
+    /// 只有当这个 Receiver 是通道唯一的持有者时才会成功: 上一轮的 Sender(包括它的所有 clone)
+    /// 都已经被丢弃，这时可以像 Channel::reset 一样独占地重置通道，未被接收的消息在这里被释放.
+    /// 旧 Sender 仍然存活时返回 Err(self)，Receiver 原样交还，仍然可以继续接收上一轮的消息.
+    /// 因为不会有旧 Sender 与新一轮共存，所以不需要用代数(generation)来区分迟到的 send:
+    /// 迟到的 send 只可能发生在 recycle 失败的情况下，落在原来那一轮中.
+    /// split_ref 得到的(借用静态 Channel 的) Receiver 同样返回 Err(self).
+    pub fn recycle(mut self) -> Result<(OwnedSender<T>, OwnedReceiver<T>), Self> {
+        let unique = match &mut self.inner {
+            ChannelRef::Shared(channel) => Arc::get_mut(channel).is_some(),
+            ChannelRef::Borrowed(_) => false,
+        };
+        if !unique {
+            return Err(self);
+        }
+        // 通道马上会被重置，不需要 Receiver 的 Drop 去标记 closed.
+        let this = mem::ManuallyDrop::new(self);
+        let ChannelRef::Shared(mut channel) = (unsafe { core::ptr::read(&this.inner) }) else {
+            unreachable!()
+        };
+        Arc::get_mut(&mut channel).expect("unique").reset();
+        Ok((
+            Sender {
+                inner: ChannelRef::Shared(channel.clone()),
+            },
+            Receiver {
+                inner: ChannelRef::Shared(channel),
+                received: false,
+                _not_sync: PhantomData,
+            },
+        ))
+    }
+}
+
+/// 由 Receiver::map 得到，成功接收时对消息调用 f.
+pub struct MappedReceiver<'a, T, U, F> {
+    inner: Receiver<'a, T>,
+    // f : 第一次成功接收时被取走，之后的接收与 Receiver 一样不会再得到消息.
+    f: Option<F>,
+    _output: PhantomData<fn() -> U>,
+}
+
+impl<T, U, F: FnOnce(T) -> U> MappedReceiver<'_, T, U, F> {
+    fn apply(&mut self, msg: T) -> U {
+        (self.f.take().expect("message already received"))(msg)
+    }
+
+    /// 见 Receiver::try_recv.
+    pub fn try_recv(&mut self) -> Result<U, TryRecvError> {
+        let msg = self.inner.try_recv()?;
+        Ok(self.apply(msg))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, U, F: FnOnce(T) -> U> MappedReceiver<'_, T, U, F> {
+    /// 见 Receiver::recv.
+    pub fn recv(self) -> Result<U, RecvError> {
+        let MappedReceiver { inner, f, .. } = self;
+        let msg = inner.recv()?;
+        Ok((f.expect("message already received"))(msg))
+    }
+
+    /// 见 Receiver::recv_timeout. 超时后可以用同一个 MappedReceiver 重试.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<U, RecvTimeoutError> {
+        let msg = self.inner.recv_timeout(timeout)?;
+        Ok(self.apply(msg))
+    }
+
+    /// 见 Receiver::recv_deadline.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<U, RecvTimeoutError> {
+        let msg = self.inner.recv_deadline(deadline)?;
+        Ok(self.apply(msg))
+    }
+}
+
+impl<T, U, F> fmt::Debug for MappedReceiver<'_, T, U, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedReceiver")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
     }
 }
 
+/// Receiver::on_ready 登记的回调.
+#[cfg(feature = "std")]
+type Callback<T> = Box<dyn FnOnce(T) + Send>;
+
+/// message 独占缓存行: 发送方写入消息时，不会让接收方正在读取的 ready 等状态所在的缓存行失效.
+/// repr(C) 保证字段按声明顺序排列，状态字段紧跟在 message 之后，共享下一条缓存行.
+/// 填充由默认开启的 cache-padded feature 控制，关闭后 Channel 只占消息和状态字段本身的大小.
+#[repr(C)]
 pub struct Channel<T> {
-    message: UnsafeCell<MaybeUninit<T>>,
-    // ready : 表示通道里是否有可用的元素.
-    ready: AtomicBool,
+    message: CachePadded<UnsafeCell<MaybeUninit<T>>>,
+    // state : 消息的状态(是否可用、是否断开等)，取值和转换见 state 模块.
+    state: AtomicU8,
+    // senders : 存活的 Sender 数量.
+    senders: AtomicUsize,
+    // 为了能够取消接收方的停放，发送方需要知道哪个线程要取消停放。
+    // 接收方在 park 之前把自己的线程句柄登记在这里，而不是在 split 时由 Sender 记住
+    // 当时的线程，因此 Receiver 可以被发送到其他线程之后再调用 recv.
+    #[cfg(feature = "std")]
+    waiter: Waiter,
+    // strategy : 阻塞接收在停放之前如何等待，见 wait 模块. reset 之后保持不变.
+    #[cfg(feature = "std")]
+    strategy: &'static dyn WaitStrategy,
+    // callback : Receiver::on_ready 登记的回调，由取走它的一方(send 或 on_ready 自己)调用.
+    #[cfg(feature = "std")]
+    callback: Mutex<Option<Callback<T>>>,
+    // metrics : 见 metrics 模块，第一次被用到时分配. reset 之后保持不变.
+    #[cfg(feature = "metrics")]
+    metrics: std::sync::OnceLock<Arc<ChannelMetrics>>,
+    // claimed : 是否已经通过 split_ref 交出过 Sender/Receiver.
+    claimed: AtomicBool,
+    // lent : peek / recv_ref 是否可能已经借出了对消息的引用. 置位之后 send_replace 不再移走消息.
+    lent: AtomicBool,
 }
 
 impl<T> Channel<T> {
-    pub const fn new() -> Self {
-        Self {
-            message: UnsafeCell::new(MaybeUninit::uninit()),
-            ready: AtomicBool::new(false),
+    const_fn! {
+        pub const fn new() -> Self {
+            Self {
+                message: CachePadded::new(UnsafeCell::new(MaybeUninit::uninit())),
+                state: AtomicU8::new(state::EMPTY),
+                senders: AtomicUsize::new(1),
+                #[cfg(feature = "std")]
+                waiter: Waiter::new(),
+                #[cfg(feature = "std")]
+                strategy: &SpinThenPark::DEFAULT,
+                #[cfg(feature = "std")]
+                callback: Mutex::new(None),
+                #[cfg(feature = "metrics")]
+                metrics: std::sync::OnceLock::new(),
+                claimed: AtomicBool::new(false),
+                lent: AtomicBool::new(false),
+            }
+        }
+    }
+
+    /// 创建一个已经就绪的通道，msg 就像已经被 send 过一样，类似于 std::future::ready.
+    /// 注意 split 会先重置通道，应当用 split_ref 取得 Receiver(或者直接用 take 取回消息)；
+    /// 从未被接收的消息由 Channel 的 Drop 释放.
+    pub fn with_value(msg: T) -> Self {
+        let channel = Self::new();
+        channel.message.with_mut(|ptr| unsafe { (*ptr).write(msg) });
+        channel
+            .state
+            .store(state::READY, core::sync::atomic::Ordering::Relaxed);
+        channel
+    }
+
+    #[cfg(feature = "std")]
+    const_fn! {
+        /// 与 new 相同，但阻塞接收使用 strategy 而不是默认的 SpinThenPark::DEFAULT，例如
+        /// static CHANNEL: Channel<u32> = Channel::with_wait_strategy(&SpinThenPark { spins: 1000 }).
+        /// 需要引入策略之前立即停放的行为时传入 &Park.
+        pub const fn with_wait_strategy(strategy: &'static dyn WaitStrategy) -> Self {
+            let mut channel = Self::new();
+            channel.strategy = strategy;
+            channel
         }
     }
 
@@ -80,49 +1118,1652 @@ impl<T> Channel<T> {
     /// 这将需要独占借用(&mut Channel)，以确保同一通道不能有多个发送者或接收者。
     /// 通过同时提供 Sender 和 Receiver ，我们可以将独占借用分成两个共享借用，
     /// 这样发送方和接收方都可以引用通道，同时防止其他任何东西接触通道。
+    /// split 会先重置通道，上一轮已经发送但没有被接收的消息在这里被释放(不会 panic，也不会泄漏)；
+    /// 需要取回它时使用 split_replacing，希望在有未读消息时失败则使用 try_split.
     pub fn split<'a>(&'a mut self) -> (Sender<'a, T>, Receiver<'a, T>) {
-        *self = Self::new();
+        self.reset();
+        self.claimed
+            .store(true, core::sync::atomic::Ordering::Relaxed);
         (
             Sender {
-                inner: self,
-                waiting: thread::current(),
+                inner: ChannelRef::Borrowed(self),
             },
             Receiver {
-                inner: self,
-                _marker: PhantomData,
+                inner: ChannelRef::Borrowed(self),
+                received: false,
+                _not_sync: PhantomData,
             },
         )
     }
-}
 
-unsafe impl<T> Sync for Channel<T> where T: Send {}
+    /// 与 split 相同，但通道中还有一条已经发送却没有被接收的消息时返回 None，通道保持原样，
+    /// 调用方可以先用 take 取走它. 在循环中复用同一个 Channel 时，可以防止意外地丢弃结果.
+    pub fn try_split(&mut self) -> Option<(Sender<'_, T>, Receiver<'_, T>)> {
+        // &mut self 之下没有并发访问，Relaxed 即可.
+        if self.state.load(core::sync::atomic::Ordering::Relaxed) == state::READY {
+            return None;
+        }
+        Some(self.split())
+    }
 
-impl<T> Drop for Channel<T> {
-    fn drop(&mut self) {
-        if *self.ready.get_mut() {
-            unsafe { self.message.get_mut().assume_init_drop() }
+    /// 与 split 相同，但上一轮已经发送却没有被接收的消息不会被释放，而是随新的一对 Sender/Receiver
+    /// 一起返回(先通过 take 取出，再 split)，复用同一个 Channel 时不会悄悄丢失结果.
+    pub fn split_replacing<'a>(&'a mut self) -> (Sender<'a, T>, Receiver<'a, T>, Option<T>) {
+        let leftover = self.take();
+        let (sender, receiver) = self.split();
+        (sender, receiver, leftover)
+    }
+
+    /// 与 split 相同，同时把之后的阻塞接收的等待策略换成 strategy.
+    #[cfg(feature = "std")]
+    pub fn split_with<'a>(
+        &'a mut self,
+        strategy: &'static dyn WaitStrategy,
+    ) -> (Sender<'a, T>, Receiver<'a, T>) {
+        self.strategy = strategy;
+        self.split()
+    }
+
+    /// split() 需要 &mut self，对于 static CHANNEL: Channel<T> = Channel::new() 这样的静态通道无法使用.
+    /// split_ref() 只需要 &self，用 claimed 标志保证 Sender/Receiver 只会被交出一次:
+    /// 第一个调用者得到 Some，其余的调用(包括在 split() 之后的调用)都返回 None.
+    /// 与 split() 不同，它不会重置通道，因为没有独占访问，无法确定能否安全地丢弃一条已经就绪的消息.
+    pub fn split_ref(&self) -> Option<(Sender<'_, T>, Receiver<'_, T>)> {
+        if self
+            .claimed
+            .swap(true, core::sync::atomic::Ordering::Acquire)
+        {
+            return None;
         }
+        Some((
+            Sender {
+                inner: ChannelRef::Borrowed(self),
+            },
+            Receiver {
+                inner: ChannelRef::Borrowed(self),
+                received: false,
+                _not_sync: PhantomData,
+            },
+        ))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::thread;
+    /// split() 要求 &mut self，Sender 和 Receiver 的生命周期被绑定在栈上的 Channel 上，
+    /// 只能配合 thread::scope 使用。new_shared() 把 Channel 放到 Arc 中，两端各持有一份，
+    /// 得到的 Sender<'static, T> 可以被 move 进 thread::spawn 的线程，或存放在结构体中。
+    /// 消息的释放交给 Arc: 最后一端被 drop 时，Channel 的 Drop 负责清理未被接收的消息。
+    pub fn new_shared() -> (Sender<'static, T>, Receiver<'static, T>) {
+        Self::shared(Self::new())
+    }
 
-    // import
-    use super::*;
+    /// 与 new_shared 相同，但阻塞接收使用 strategy，见 wait 模块.
+    #[cfg(feature = "std")]
+    pub fn new_shared_with(
+        strategy: &'static dyn WaitStrategy,
+    ) -> (Sender<'static, T>, Receiver<'static, T>) {
+        Self::shared(Self::with_wait_strategy(strategy))
+    }
 
-    // testing
-    #[test]
-    fn it_works() {
-        let mut channel = Channel::new();
-        thread::scope(|s| {
-            let (sender, receiver) = channel.split();
-            s.spawn(move || {
-                sender.send("hello rustacean!");
-            });
-            // Print Receive message.
-            assert_eq!(receiver.recv(), "hello rustacean!");
+    fn shared(channel: Self) -> (Sender<'static, T>, Receiver<'static, T>) {
+        let channel = Arc::new(channel);
+        (
+            Sender {
+                inner: ChannelRef::Shared(channel.clone()),
+            },
+            Receiver {
+                inner: ChannelRef::Shared(channel),
+                received: false,
+                _not_sync: PhantomData,
+            },
+        )
+    }
+
+    /// 用 Acquire 读取当前状态: 读到 READY 时消息已经完整写入，读到 CANCELLED / CLOSED_BY_SENDER 时
+    /// 与对应转换的 Release 配对.
+    fn state(&self) -> u8 {
+        self.state.load(core::sync::atomic::Ordering::Acquire)
+    }
+
+    /// 消息进入 READY 之后唤醒等待方；登记的是 on_ready 的回调时，在当前线程上调用它.
+    #[cfg(feature = "std")]
+    fn notify(&self) {
+        if self.waiter.unpark() {
+            self.run_callback();
+        }
+    }
+
+    /// send 与 on_ready 都可能看到"回调已登记并且消息已经就绪"，谁先从 callback 中取走回调谁调用它.
+    /// 在锁内对 READY 做 compare_exchange: 失败说明 send_replace 正在覆盖消息，回调留在原处，
+    /// 由那次覆盖完成之后的 notify 调用. 回调在释放锁之后才被调用，它 panic 时不会毒化 Mutex.
+    #[cfg(feature = "std")]
+    fn run_callback(&self) {
+        let mut callback = self.callback.lock().unwrap();
+        if callback.is_none()
+            || self
+                .state
+                .compare_exchange(
+                    state::READY,
+                    state::READING,
+                    core::sync::atomic::Ordering::Acquire,
+                    core::sync::atomic::Ordering::Relaxed,
+                )
+                .is_err()
+        {
+            return;
+        }
+        let f = callback.take().unwrap();
+        let msg = self
+            .message
+            .with(|ptr| unsafe { (*ptr).assume_init_read() });
+        self.state
+            .store(state::RECEIVED, core::sync::atomic::Ordering::Relaxed);
+        drop(callback);
+        metrics::record!(self, recvs);
+        f(msg);
+    }
+
+    /// 这个通道的统计，返回的 Arc 可以交给监控代码长期持有，之后的计数仍会反映在其中.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Arc<ChannelMetrics> {
+        self.metrics_ref().clone()
+    }
+
+    #[cfg(feature = "metrics")]
+    fn metrics_ref(&self) -> &Arc<ChannelMetrics> {
+        self.metrics.get_or_init(Default::default)
+    }
+
+    /// 把通道恢复成新创建时的状态，以便在热路径上复用同一块存储: 未被接收的消息会在这里被释放.
+    /// &mut self 保证调用时不存在仍然借用着通道的 Sender/Receiver；
+    /// 在 Receiver 没有接收就被丢弃之后调用也是安全的.
+    pub fn reset(&mut self) {
+        #[cfg(feature = "std")]
+        let strategy = self.strategy;
+        #[cfg(feature = "metrics")]
+        let metrics = mem::take(&mut self.metrics);
+        *self = Self::new();
+        #[cfg(feature = "std")]
+        {
+            self.strategy = strategy;
+        }
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics = metrics;
+        }
+    }
+
+    /// 取回一条已经发送但没有被接收的消息(例如 Receiver 在更高层超时后被丢弃)，并重置通道.
+    /// &mut self 保证此时 Sender/Receiver 都已不存在，不会有并发访问.
+    /// 之后可以重新 split() 或 split_ref()，就像一个新创建的通道.
+    pub fn take(&mut self) -> Option<T> {
+        // &mut self 之下没有并发访问，Relaxed 即可.
+        let msg = if self.state.load(core::sync::atomic::Ordering::Relaxed) == state::READY {
+            Some(
+                self.message
+                    .with_mut(|ptr| unsafe { (*ptr).assume_init_read() }),
+            )
+        } else {
+            None
+        };
+        // 消息已经被移出，先离开 READY，旧状态被丢弃时 Drop 不会再次释放消息.
+        self.state
+            .store(state::RECEIVED, core::sync::atomic::Ordering::Relaxed);
+        self.reset();
+        msg
+    }
+}
+
+impl<T: fmt::Debug> Channel<T> {
+    /// 打印通道中还没被接收的消息(没有时打印 None)，用于调试. 需要 &mut self:
+    /// 通过 &self 读取消息可能与 split_ref 得到的 Receiver 并发取走它的 try_recv 竞争.
+    pub fn debug_value(&mut self) -> impl fmt::Debug + '_ {
+        // &mut self 之下没有并发访问，Relaxed 即可.
+        let ready = self.state.load(core::sync::atomic::Ordering::Relaxed) == state::READY;
+        ready.then(|| self.message.with(|ptr| unsafe { (*ptr).assume_init_ref() }))
+    }
+}
+
+/// 只打印状态标志，不打印消息本身: 那需要 T: Debug，而且可能与并发的 send 竞争.
+/// 各标志用 Relaxed 读取，只是某一时刻的快照.
+impl<T> fmt::Debug for Channel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.load(core::sync::atomic::Ordering::Relaxed);
+        let mut d = f.debug_struct("Channel");
+        d.field("ready", &(state == state::READY))
+            .field("state", &format_args!("{}", state::name(state)));
+        #[cfg(feature = "std")]
+        d.field("waiter", &self.waiter);
+        d.finish_non_exhaustive()
+    }
+}
+
+impl<T> Default for Channel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<T> Sync for Channel<T> where T: Send {}
+
+impl<T> Drop for Channel<T> {
+    /// 只有状态仍是 READY 时消息才没有被取走，此时释放它. RECEIVED 等其他状态下 message 已经被移出或从未写入.
+    /// &mut self 之下没有并发访问，Relaxed 即可: 最后一次 send 的 Release 已经通过 Arc 的引用计数
+    /// 或 thread::scope 的 join 与这里同步.
+    fn drop(&mut self) {
+        if self.state.load(core::sync::atomic::Ordering::Relaxed) == state::READY {
+            self.message
+                .with_mut(|ptr| unsafe { (*ptr).assume_init_drop() })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", not(loom)))]
+mod test {
+    use std::task::Wake;
+    use std::thread;
+
+    // import
+    use super::*;
+
+    /// 被释放时给 drops 加一，用来检查消息既没有丢失也没有被释放两次. 每个测试各自创建 drops，
+    /// 并行运行的测试之间互不干扰. value 放在堆上，重复释放或泄漏在 Miri 下也会被发现.
+    struct DropCounter {
+        value: Box<usize>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl DropCounter {
+        fn new(value: usize, drops: &Arc<AtomicUsize>) -> Self {
+            Self {
+                value: Box::new(value),
+                drops: drops.clone(),
+            }
+        }
+
+        fn value(&self) -> usize {
+            *self.value
+        }
+    }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.drops
+                .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn drops(drops: &AtomicUsize) -> usize {
+        drops.load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    // testing
+    #[test]
+    fn it_works() {
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            s.spawn(move || {
+                sender.send("hello rustacean!").unwrap();
+            });
+            // Print Receive message.
+            assert_eq!(receiver.recv(), Ok("hello rustacean!"));
+        });
+    }
+
+    #[test]
+    fn shared_works() {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || {
+            sender.send(String::from("hello rustacean!")).unwrap();
+        });
+        assert_eq!(receiver.recv().unwrap(), "hello rustacean!");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn try_recv_works() {
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, mut receiver) = channel.split();
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+            s.spawn(move || {
+                sender.send("hello rustacean!").unwrap();
+            });
+            let msg = loop {
+                match receiver.try_recv() {
+                    Ok(msg) => break msg,
+                    Err(TryRecvError::Empty) => thread::yield_now(),
+                    Err(TryRecvError::Disconnected | TryRecvError::Cancelled) => unreachable!(),
+                }
+            };
+            assert_eq!(msg, "hello rustacean!");
+            // 消息只能被取走一次.
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        });
+    }
+
+    #[test]
+    fn try_recv_races_with_send() {
+        // 通过计数 Drop 的次数来检查消息既没有丢失，也没有被读取两次.
+        let dropped = Arc::new(AtomicUsize::new(0));
+        const ROUNDS: usize = 200;
+        for i in 0..ROUNDS {
+            let mut channel = Channel::new();
+            thread::scope(|s| {
+                let (sender, mut receiver) = channel.split();
+                let msg = DropCounter::new(i, &dropped);
+                s.spawn(move || sender.send(msg).unwrap());
+                let msg = loop {
+                    if let Ok(msg) = receiver.try_recv() {
+                        break msg;
+                    }
+                    thread::yield_now();
+                };
+                assert_eq!(msg.value(), i);
+                assert!(receiver.try_recv().is_err());
+            });
+        }
+        assert_eq!(drops(&dropped), ROUNDS);
+    }
+
+    #[test]
+    fn recv_disconnected() {
+        let mut channel = Channel::<i32>::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            s.spawn(move || drop(sender));
+            assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        });
+
+        let mut channel = Channel::<i32>::new();
+        let (sender, mut receiver) = channel.split();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn recv_timeout_works() {
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            // 消息在截止时间之前到达.
+            let (sender, mut receiver) = channel.split();
+            s.spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                sender.send(1).unwrap();
+            });
+            assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+        });
+
+        thread::scope(|s| {
+            // 消息在截止时间之后到达: 先超时，之后仍然可以用同一个 Receiver 收到消息.
+            let (sender, mut receiver) = channel.split();
+            s.spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                sender.send(2).unwrap();
+            });
+            let deadline = Instant::now() + Duration::from_millis(10);
+            assert_eq!(
+                receiver.recv_deadline(deadline),
+                Err(RecvTimeoutError::Timeout)
+            );
+            assert!(Instant::now() >= deadline);
+            assert_eq!(receiver.recv(), Ok(2));
+        });
+
+        // Sender 一直不发送.
+        let (sender, mut receiver) = channel.split();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(sender);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn recv_timeout_spurious_wakeup() {
+        let mut channel = Channel::<i32>::new();
+        let (_sender, mut receiver) = channel.split();
+        let waiting = thread::current();
+        thread::scope(|s| {
+            // 不断地 unpark 接收线程，模拟虚假唤醒.
+            s.spawn(move || {
+                for _ in 0..10 {
+                    waiting.unpark();
+                    thread::sleep(Duration::from_millis(2));
+                }
+            });
+            let start = Instant::now();
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_millis(50)),
+                Err(RecvTimeoutError::Timeout)
+            );
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        });
+    }
+
+    #[test]
+    fn recv_after_sender_panic() {
+        let (sender, receiver) = channel::<i32>();
+        let handle = thread::spawn(move || {
+            let _sender = sender;
+            panic!("worker failed before sending");
+        });
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        assert!(handle.join().is_err());
+    }
+
+    #[test]
+    fn send_after_receiver_dropped() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut channel = Channel::new();
+        let (sender, receiver) = channel.split();
+        drop(receiver);
+        let SendError(msg) = sender.send(DropCounter::new(42, &dropped)).unwrap_err();
+        assert_eq!(msg.value(), 42);
+        assert_eq!(drops(&dropped), 0);
+        drop(msg);
+        drop(channel);
+        assert_eq!(drops(&dropped), 1);
+    }
+
+    #[test]
+    fn shared_drops_message_once() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+
+        // 消息未被接收: 最后一个 Arc 被释放时，由 Channel 的 Drop 释放消息.
+        let (sender, receiver) = channel();
+        let msg = DropCounter::new(0, &dropped);
+        thread::spawn(move || assert!(sender.send(msg).is_ok()))
+            .join()
+            .unwrap();
+        assert_eq!(drops(&dropped), 0);
+        drop(receiver);
+        assert_eq!(drops(&dropped), 1);
+
+        // 消息被接收后，由接收方释放.
+        let (sender, receiver) = channel();
+        assert!(sender.send(DropCounter::new(1, &dropped)).is_ok());
+        drop(receiver.recv().unwrap());
+        assert_eq!(drops(&dropped), 2);
+    }
+
+    #[test]
+    fn recv_on_another_thread() {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || receiver.recv());
+        thread::sleep(Duration::from_millis(10));
+        sender.send(42).unwrap();
+        assert_eq!(handle.join().unwrap(), Ok(42));
+
+        // 消息在 Receiver 开始等待之前就已经到达.
+        let (sender, receiver) = channel();
+        sender.send(42).unwrap();
+        let handle = thread::spawn(move || receiver.recv());
+        assert_eq!(handle.join().unwrap(), Ok(42));
+    }
+
+    #[test]
+    fn halves_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Sender<'static, String>>();
+        assert_send::<Receiver<'static, String>>();
+        assert_send::<OwnedReceiver<String>>();
+    }
+
+    #[test]
+    fn split_ref_static() {
+        static CHANNEL: Channel<i32> = Channel::new();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| thread::spawn(|| CHANNEL.split_ref()))
+            .collect();
+        let mut pairs: Vec<_> = handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(pairs.len(), 1);
+        assert!(CHANNEL.split_ref().is_none());
+
+        let (sender, receiver) = pairs.pop().unwrap();
+        thread::spawn(move || sender.send(42).unwrap());
+        assert_eq!(receiver.recv(), Ok(42));
+    }
+
+    /// 一个最简单的执行器: 用当前线程的 park/unpark 作为 Waker，反复 poll 直到 Future 完成.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct ThreadWaker(Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let mut future = std::pin::pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn await_receiver() {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send(42).unwrap();
+        });
+        assert_eq!(block_on(receiver), Ok(42));
+
+        let (sender, receiver) = channel::<i32>();
+        thread::spawn(move || drop(sender));
+        assert_eq!(block_on(receiver), Err(RecvError::Disconnected));
+
+        // Receiver 本身就是 Future，因此自动实现了 IntoFuture.
+        use std::future::IntoFuture;
+        let (sender, receiver) = channel();
+        sender.send(7).unwrap();
+        assert_eq!(block_on(receiver.into_future()), Ok(7));
+    }
+
+    #[test]
+    fn cloned_senders_first_wins() {
+        let (sender, receiver) = channel();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let sender = sender.clone();
+                thread::spawn(move || sender.send(i))
+            })
+            .collect();
+        drop(sender);
+        let msg = receiver.recv().unwrap();
+        let mut losers: Vec<_> = handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap().err())
+            .map(|SendError(i)| i)
+            .collect();
+        losers.push(msg);
+        losers.sort();
+        assert_eq!(losers, (0..8).collect::<Vec<_>>());
+
+        // 所有 clone 都没有发送就被丢弃.
+        let (sender, receiver) = channel::<i32>();
+        let clones: Vec<_> = (0..4).map(|_| sender.clone()).collect();
+        drop(sender);
+        thread::spawn(move || drop(clones));
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn peek_then_recv() {
+        let mut channel = Channel::new();
+        let (sender, receiver) = channel.split();
+        assert_eq!(receiver.peek(), None);
+        sender.send(String::from("hello rustacean!")).unwrap();
+        assert_eq!(
+            receiver.peek().map(String::as_str),
+            Some("hello rustacean!")
+        );
+        assert_eq!(receiver.peek().map(String::len), Some(16));
+        assert_eq!(receiver.recv().unwrap(), "hello rustacean!");
+
+        let (sender, mut receiver) = channel.split();
+        sender.send(String::from("again")).unwrap();
+        assert_eq!(receiver.peek().map(String::as_str), Some("again"));
+        assert_eq!(receiver.try_recv().as_deref(), Ok("again"));
+        assert_eq!(receiver.peek(), None);
+    }
+
+    #[test]
+    fn close_receiver() {
+        let (sender, receiver) = channel();
+        receiver.close();
+        assert_eq!(
+            sender.send(String::from("unused")),
+            Err(SendError(String::from("unused")))
+        );
+
+        let value = Arc::new(());
+        let (sender, receiver) = channel();
+        sender.send(value.clone()).unwrap();
+        receiver.close();
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn send_with_works() {
+        let (sender, receiver) = channel();
+        sender.send_with(|| [7u8; 4096]).unwrap();
+        assert_eq!(receiver.recv().unwrap(), [7u8; 4096]);
+
+        // 初始化函数 panic: 通道保持未就绪，唯一的 Sender 被消耗，接收方收到 Disconnected.
+        let (sender, mut receiver) = channel::<Arc<()>>();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            sender.send_with(|| panic!("initializer failed"))
+        }));
+        assert!(result.is_err());
+        assert!(!receiver.is_ready());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+
+        // 还有其他 Sender 时，panic 不会占用发送的权利.
+        let (sender, receiver) = channel();
+        let other = sender.clone();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            sender.send_with(|| panic!("initializer failed"))
+        }));
+        assert!(result.is_err());
+        other.send(1).unwrap();
+        assert_eq!(receiver.recv(), Ok(1));
+    }
+
+    #[test]
+    fn take_unreceived() {
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            s.spawn(move || sender.send(String::from("late")).unwrap())
+                .join()
+                .unwrap();
+            // Receiver 没有接收就被丢弃了.
+            drop(receiver);
+        });
+        assert_eq!(channel.take().as_deref(), Some("late"));
+        assert_eq!(channel.take(), None);
+
+        // 重置之后可以再次使用.
+        let (sender, receiver) = channel.split_ref().unwrap();
+        sender.send(String::from("again")).unwrap();
+        assert_eq!(receiver.recv().as_deref(), Ok("again"));
+    }
+
+    #[test]
+    fn reset_and_reuse() {
+        let value = Arc::new(());
+        let mut channel = Channel::new();
+
+        // 成功接收之后重置.
+        let (sender, receiver) = channel.split();
+        sender.send(value.clone()).unwrap();
+        drop(receiver.recv().unwrap());
+        channel.reset();
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // 消息发送之后没有被接收，重置时释放它.
+        let (sender, receiver) = channel.split();
+        sender.send(value.clone()).unwrap();
+        drop(receiver);
+        assert_eq!(Arc::strong_count(&value), 2);
+        channel.reset();
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (sender, receiver) = channel.split();
+        sender.send(value.clone()).unwrap();
+        assert!(receiver.recv().is_ok());
+    }
+
+    #[test]
+    fn debug_shows_state() {
+        let (sender, receiver) = channel::<String>();
+        let debug = format!("{sender:?}");
+        assert!(debug.contains("ready: false"), "{debug}");
+        assert!(debug.contains("receiver_alive: true"), "{debug}");
+        drop(receiver);
+        assert!(format!("{sender:?}").contains("receiver_alive: false"));
+
+        let mut channel = Channel::new();
+        let (sender, receiver) = channel.split();
+        sender.send(String::from("secret")).unwrap();
+        let debug = format!("{receiver:?}");
+        assert!(debug.contains("ready: true"), "{debug}");
+        assert!(debug.contains("senders_alive: false"), "{debug}");
+        assert!(!debug.contains("secret"));
+        drop(receiver);
+        let debug = format!("{channel:?}");
+        assert!(debug.starts_with("Channel { ready: true"), "{debug}");
+        assert!(!debug.contains("secret"));
+    }
+
+    #[test]
+    fn cancel_before_park() {
+        let (sender, mut receiver) = channel::<i32>();
+        assert!(!receiver.is_cancelled());
+        sender.cancel();
+        assert!(receiver.is_cancelled());
+        assert!(!receiver.is_ready());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Cancelled));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Cancelled)
+        );
+        assert_eq!(receiver.recv(), Err(RecvError::Cancelled));
+
+        // cancel 之后，其余 Sender 的 send 会失败; 已经发送的消息不会被 cancel 覆盖.
+        let (sender, receiver) = channel();
+        let other = sender.clone();
+        sender.cancel();
+        assert_eq!(other.send(1), Err(SendError(1)));
+        assert_eq!(receiver.recv(), Err(RecvError::Cancelled));
+
+        let (sender, receiver) = channel();
+        let other = sender.clone();
+        sender.send(1).unwrap();
+        other.cancel();
+        assert_eq!(receiver.recv(), Ok(1));
+    }
+
+    #[test]
+    fn cancel_while_parked() {
+        let (sender, receiver) = channel::<i32>();
+        let handle = thread::spawn(move || receiver.recv());
+        thread::sleep(Duration::from_millis(10));
+        sender.cancel();
+        assert_eq!(handle.join().unwrap(), Err(RecvError::Cancelled));
+
+        #[cfg(feature = "async")]
+        {
+            let (sender, receiver) = channel::<i32>();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                sender.cancel();
+            });
+            assert_eq!(block_on(receiver), Err(RecvError::Cancelled));
+        }
+    }
+
+    #[test]
+    fn cancel_races_with_try_recv() {
+        for _ in 0..200 {
+            let (sender, mut receiver) = channel::<i32>();
+            let handle = thread::spawn(move || sender.cancel());
+            loop {
+                match receiver.try_recv() {
+                    Err(TryRecvError::Empty) => thread::yield_now(),
+                    result => {
+                        assert_eq!(result, Err(TryRecvError::Cancelled));
+                        break;
+                    }
+                }
+            }
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn recv_ref_leaves_message_in_place() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            let msg = DropCounter::new(42, &dropped);
+            s.spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                sender.send(msg).unwrap();
+            });
+            assert_eq!(receiver.recv_ref().unwrap().value(), 42);
+            assert_eq!(receiver.recv_ref().unwrap().value(), 42);
+            assert!(receiver.is_ready());
+        });
+        assert_eq!(drops(&dropped), 0);
+        drop(channel);
+        assert_eq!(drops(&dropped), 1);
+
+        // 引用结束之后仍然可以把消息移出来，只会释放一次.
+        let (sender, receiver) = crate::channel();
+        sender.send(DropCounter::new(7, &dropped)).unwrap();
+        assert_eq!(receiver.recv_ref().unwrap().value(), 7);
+        let msg = receiver.recv().unwrap();
+        assert_eq!(msg.value(), 7);
+        drop(msg);
+        assert_eq!(drops(&dropped), 2);
+
+        let (sender, receiver) = crate::channel::<DropCounter>();
+        drop(sender);
+        assert!(matches!(receiver.recv_ref(), Err(RecvError::Disconnected)));
+    }
+
+    #[test]
+    fn wait_ready_then_recv() {
+        let (sender, receiver) = channel();
+        assert!(!receiver.wait_ready(Some(Duration::from_millis(10))));
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send(42).unwrap();
+        });
+        assert!(receiver.wait_ready(None));
+        assert!(receiver.is_ready());
+        // 在另一个线程上消费，不需要再等待.
+        assert_eq!(
+            thread::spawn(move || receiver.recv()).join().unwrap(),
+            Ok(42)
+        );
+        handle.join().unwrap();
+
+        let (sender, receiver) = channel::<i32>();
+        sender.cancel();
+        assert!(!receiver.wait_ready(None));
+    }
+
+    #[test]
+    fn debug_without_t_debug() {
+        struct Opaque;
+
+        // T 没有实现 Debug; Sender 打印出正在等待的接收线程.
+        let (sender, receiver) = channel::<Opaque>();
+        let handle = thread::spawn(move || receiver.recv().is_ok());
+        let waiting = loop {
+            let debug = format!("{sender:?}");
+            if debug.contains("waiting: Some(Thread(") {
+                break debug;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert!(waiting.contains(&format!("{:?}", handle.thread().id())));
+        sender.send(Opaque).ok();
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn same_channel_pairs_up_halves() {
+        use std::collections::HashMap;
+
+        let mut borrowed = [Channel::<i32>::new(), Channel::new()];
+        let [first, second] = &mut borrowed;
+        let mut senders = Vec::new();
+        let mut receivers = Vec::new();
+        for (sender, receiver) in [first.split(), second.split()]
+            .into_iter()
+            .chain((0..3).map(|_| channel()))
+        {
+            senders.push(sender);
+            receivers.push(receiver);
+        }
+        senders.reverse();
+        receivers.swap(0, 3);
+
+        let by_id: HashMap<_, _> = receivers
+            .iter()
+            .enumerate()
+            .map(|(i, receiver)| (receiver.channel_id(), i))
+            .collect();
+        assert_eq!(by_id.len(), 5);
+        for sender in &senders {
+            let receiver = &receivers[by_id[&sender.channel_id()]];
+            assert!(sender.same_channel(receiver));
+            assert!(receiver.same_channel(sender));
+            let others = receivers.iter().filter(|rx| sender.same_channel(rx));
+            assert_eq!(others.count(), 1);
+        }
+        // clone 出来的 Sender 仍然属于同一个通道.
+        assert!(senders[0]
+            .clone()
+            .same_channel(&receivers[by_id[&senders[0].channel_id()]]));
+    }
+
+    #[test]
+    fn map_transforms_message() {
+        let (sender, receiver) = channel();
+        let receiver = receiver.map(|s: &str| s.parse::<i32>());
+        sender.send("42").unwrap();
+        assert_eq!(receiver.recv(), Ok(Ok(42)));
+
+        let (sender, receiver) = channel::<i32>();
+        let mut receiver = receiver.map(|n| n * 2);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+
+        let (sender, receiver) = channel();
+        let mut receiver = receiver.map(|n: i32| n + 1);
+        sender.send(1).unwrap();
+        assert_eq!(
+            receiver.recv_deadline(Instant::now() + Duration::from_millis(5)),
+            Ok(2)
+        );
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn recycle_reuses_allocation() {
+        let (sender, mut receiver) = channel();
+        let id = receiver.channel_id();
+        sender.send(1).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        let (sender, receiver) = receiver.recycle().unwrap();
+        assert_eq!(receiver.channel_id(), id);
+        assert!(!receiver.is_ready());
+        sender.send(2).unwrap();
+        assert_eq!(receiver.recv(), Ok(2));
+
+        // 上一轮的 Sender 还活着: recycle 失败，迟到的 send 落在原来那一轮.
+        let (sender, receiver) = channel();
+        let stale = sender.clone();
+        drop(sender);
+        let receiver = receiver.recycle().unwrap_err();
+        let handle = thread::spawn(move || stale.send(String::from("late")));
+        handle.join().unwrap().unwrap();
+        assert!(receiver.is_ready());
+        // 未被接收的消息在重置时被释放，新的一轮从空的通道开始.
+        let (sender, receiver) = receiver.recycle().unwrap();
+        assert!(!receiver.is_ready());
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+
+        static CHANNEL: Channel<i32> = Channel::new();
+        let (_sender, receiver) = CHANNEL.split_ref().unwrap();
+        assert!(receiver.recycle().is_err());
+    }
+
+    #[test]
+    fn state_transition_matrix() {
+        #[derive(Debug, Clone, Copy)]
+        enum Op {
+            Send,
+            Cancel,
+            DropSenders,
+            DropReceiver,
+            TryRecv,
+        }
+        use Op::*;
+
+        let cases: &[(&[Op], u8)] = &[
+            (&[], state::EMPTY),
+            (&[Send], state::READY),
+            (&[Send, TryRecv], state::RECEIVED),
+            (&[Send, TryRecv, Send], state::RECEIVED),
+            (&[Send, Send], state::READY),
+            (&[Send, Cancel], state::READY),
+            (&[Send, DropSenders], state::READY),
+            (&[Send, DropReceiver], state::READY),
+            (&[TryRecv], state::EMPTY),
+            (&[Cancel], state::CANCELLED),
+            (&[Cancel, Send], state::CANCELLED),
+            (&[Cancel, DropSenders], state::CANCELLED),
+            (&[Cancel, TryRecv], state::CANCELLED),
+            (&[DropSenders], state::CLOSED_BY_SENDER),
+            (&[DropSenders, TryRecv], state::CLOSED_BY_SENDER),
+            (&[DropSenders, DropReceiver], state::CLOSED_BY_SENDER),
+            (&[DropReceiver], state::CLOSED_BY_RECEIVER),
+            (&[DropReceiver, Send], state::CLOSED_BY_RECEIVER),
+            (&[DropReceiver, Cancel], state::CLOSED_BY_RECEIVER),
+            (&[DropReceiver, DropSenders], state::CLOSED_BY_RECEIVER),
+        ];
+        for &(ops, expected) in cases {
+            let mut channel = Channel::<i32>::new();
+            let (sender, receiver) = channel.split();
+            let mut senders = vec![sender.clone(), sender];
+            let mut receiver = Some(receiver);
+            for op in ops {
+                match op {
+                    Send => {
+                        if let Some(sender) = senders.pop() {
+                            let _ = sender.send(1);
+                        }
+                    }
+                    Cancel => {
+                        if let Some(sender) = senders.pop() {
+                            sender.cancel();
+                        }
+                    }
+                    DropSenders => senders.clear(),
+                    DropReceiver => receiver = None,
+                    TryRecv => {
+                        let _ = receiver.as_mut().unwrap().try_recv();
+                    }
+                }
+            }
+            // 不运行剩下的 Sender/Receiver 的 Drop，读到的就是这一串操作之后的状态.
+            // 只 forget 其中的 Sender，Vec 本身照常释放，Miri 不会报告泄漏.
+            senders.into_iter().for_each(mem::forget);
+            mem::forget(receiver);
+            assert_eq!(
+                state::name(channel.state.load(core::sync::atomic::Ordering::Relaxed)),
+                state::name(expected),
+                "{ops:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn send_with_after_receiver_dropped() {
+        let (sender, receiver) = channel::<String>();
+        drop(receiver);
+        // 没有接收方时不会调用 f，闭包原样交还，调用方仍然可以自己调用它.
+        let called = Cell::new(false);
+        let payload = String::from("hello rustacean!");
+        let SendError(f) = sender
+            .send_with(|| {
+                called.set(true);
+                payload
+            })
+            .unwrap_err();
+        assert!(!called.get());
+        assert_eq!(f(), "hello rustacean!");
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let (sender, receiver) = channel::<String>();
+        let sender_ptr = sender.into_raw();
+        let receiver_ptr = receiver.into_raw();
+        // 两端都还没有被丢弃，通道保持 EMPTY.
+        let sender = unsafe { OwnedSender::<String>::from_raw(sender_ptr) };
+        sender.send(String::from("from C")).unwrap();
+        let receiver = unsafe { OwnedReceiver::<String>::from_raw(receiver_ptr) };
+        assert_eq!(receiver.recv().as_deref(), Ok("from C"));
+
+        // 取走消息之后再经过一次 into_raw / from_raw，Receiver 仍然记得消息已经被取走.
+        let (sender, mut receiver) = channel();
+        sender.send(1).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        let receiver = unsafe { OwnedReceiver::<i32>::from_raw(receiver.into_raw()) };
+        assert!(format!("{receiver:?}").contains("received: true"));
+    }
+
+    #[test]
+    fn parked_receiver_wakes_on_last_sender_drop() {
+        let (sender, receiver) = channel::<i32>();
+        let other = sender.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            drop(sender);
+            // 还有一个 Sender 存活，此时接收方必须继续等待.
+            thread::sleep(Duration::from_millis(10));
+            drop(other);
+        });
+        let start = Instant::now();
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn send_replace_overwrites_unread() {
+        let (sender, receiver) = channel::<String>();
+        assert_eq!(sender.send_replace("a".into()), Ok(None));
+        assert_eq!(sender.send_replace("b".into()), Ok(Some("a".into())));
+        assert_eq!(sender.send_replace("c".into()), Ok(Some("b".into())));
+        assert_eq!(receiver.recv(), Ok("c".into()));
+        // 消息已经被取走，新消息交还给调用者.
+        assert_eq!(sender.send_replace("d".into()).unwrap_err().0, "d");
+
+        let (sender, receiver) = channel::<i32>();
+        drop(receiver);
+        assert_eq!(sender.send_replace(1).unwrap_err().0, 1);
+
+        // 与接收方竞争: 每条消息要么被接收，要么被交还，正好一次.
+        for _ in 0..20 {
+            let (sender, receiver) = channel::<i32>();
+            let handle = thread::spawn(move || receiver.recv().ok());
+            let mut returned = Vec::new();
+            for i in 0..10 {
+                match sender.send_replace(i) {
+                    Ok(Some(old)) => returned.push(old),
+                    Ok(None) => {}
+                    Err(err) => returned.push(err.0),
+                }
+            }
+            returned.extend(handle.join().unwrap());
+            returned.sort();
+            assert_eq!(returned, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    /// peek / recv_ref 借出的引用存活期间，send_replace 不能移走或覆盖它指向的消息.
+    /// 用 cargo miri test 运行时，修复之前这里会报告 Undefined Behavior.
+    #[test]
+    fn send_replace_refused_while_peeked() {
+        let (sender, mut receiver) = channel::<String>();
+        let other = sender.clone();
+        sender.send(String::from("peeked")).unwrap();
+        let peeked = receiver.peek().unwrap();
+        let refused = thread::spawn(move || {
+            let refused = other.send_replace(String::from("replacement"));
+            (refused.map_err(SendError::into_inner), other)
+        });
+        let (refused, other) = refused.join().unwrap();
+        assert_eq!(refused.unwrap_err(), "replacement");
+        assert_eq!(peeked, "peeked");
+        assert_eq!(receiver.recv_ref().map(String::as_str), Ok("peeked"));
+        assert_eq!(
+            other.send_replace(String::from("late")).unwrap_err().0,
+            "late"
+        );
+        assert_eq!(receiver.try_recv().as_deref(), Ok("peeked"));
+
+        // 没有借出过引用时仍然可以覆盖.
+        let (sender, mut receiver) = channel::<i32>();
+        assert_eq!(receiver.peek(), None);
+        sender.send_replace(1).unwrap();
+        assert_eq!(sender.send_replace(2), Ok(Some(1)));
+        assert_eq!(receiver.recv_ref(), Ok(&2));
+        assert_eq!(sender.send_replace(3).unwrap_err().0, 3);
+    }
+
+    // 依赖 1ms 的超时在自旋结束之前不会到期，Miri 下执行太慢，这个假设不成立.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn wait_strategies() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        /// 记录被调用的次数，自旋 5 次之后停放.
+        struct Counting(AtomicU32);
+        impl WaitStrategy for Counting {
+            fn spin(&self, attempt: u32) -> bool {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                attempt < 5
+            }
+        }
+        static COUNTING: Counting = Counting(AtomicU32::new(0));
+
+        let mut channel = Channel::<i32>::new();
+        let (sender, mut receiver) = channel.split_with(&COUNTING);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(COUNTING.0.load(Ordering::Relaxed), 6);
+        drop((sender, receiver));
+        // reset 之后策略保持不变.
+        channel.reset();
+        let (sender, mut receiver) = channel.split();
+        // 自旋一次之后就发现已经超时.
+        assert!(receiver.recv_timeout(Duration::ZERO).is_err());
+        drop(sender);
+        assert_eq!(COUNTING.0.load(Ordering::Relaxed), 7);
+
+        for strategy in [
+            &Park as &dyn WaitStrategy,
+            &Spin,
+            &SpinThenPark { spins: 100 },
+        ] {
+            let mut channel = Channel::new();
+            let received = thread::scope(|s| {
+                let (sender, receiver) = channel.split_with(strategy);
+                let handle = s.spawn(move || receiver.recv());
+                thread::sleep(Duration::from_millis(1));
+                sender.send(7).unwrap();
+                handle.join().unwrap()
+            });
+            assert_eq!(received, Ok(7));
+        }
+
+        // Spin 从不停放，但仍然遵守超时.
+        static SPIN: Channel<i32> = Channel::with_wait_strategy(&Spin);
+        let (_sender, mut receiver) = SPIN.split_ref().unwrap();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    fn split_replacing_returns_leftover() {
+        let mut channel = Channel::new();
+        let (sender, receiver, leftover) = channel.split_replacing();
+        assert_eq!(leftover, None);
+        sender.send(String::from("first")).unwrap();
+        // 这一轮没有接收.
+        drop(receiver);
+        let (sender, receiver, leftover) = channel.split_replacing();
+        assert_eq!(leftover.as_deref(), Some("first"));
+        sender.send(String::from("second")).unwrap();
+        assert_eq!(receiver.recv().as_deref(), Ok("second"));
+        let (_, _, leftover) = channel.split_replacing();
+        assert_eq!(leftover, None);
+    }
+
+    #[test]
+    fn into_iter_yields_once() {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(5));
+            sender.send(3).unwrap();
+        });
+        let mut iter = receiver.into_iter();
+        assert_eq!(iter.size_hint(), (0, Some(1)));
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), None);
+        handle.join().unwrap();
+
+        let (sender, receiver) = channel();
+        sender.send(1).unwrap();
+        let doubled: Vec<i32> = receiver.into_iter().map(|x| x * 2).collect();
+        assert_eq!(doubled, [2]);
+    }
+
+    #[test]
+    fn into_iter_disconnected() {
+        let (sender, receiver) = channel::<i32>();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(5));
+            drop(sender);
+        });
+        let mut count = 0;
+        for _ in receiver {
+            count += 1;
+        }
+        assert_eq!(count, 0);
+        handle.join().unwrap();
+
+        let (sender, mut receiver) = channel();
+        sender.send(1).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.into_iter().next(), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn poll_recv_and_poll_ready() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingWaker(AtomicUsize);
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let first = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let second = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let first_waker = first.clone().into();
+        let second_waker = second.clone().into();
+
+        let (sender, mut receiver) = channel();
+        let mut cx = Context::from_waker(&first_waker);
+        assert!(receiver.poll_ready(&mut cx).is_pending());
+        assert!(receiver.poll_recv(&mut cx).is_pending());
+        // 换了一个任务来 poll，只有最后登记的 Waker 会被唤醒.
+        let mut cx = Context::from_waker(&second_waker);
+        assert!(receiver.poll_recv(&mut cx).is_pending());
+        sender.send(5).unwrap();
+        assert_eq!(first.0.load(Ordering::SeqCst), 0);
+        assert_eq!(second.0.load(Ordering::SeqCst), 1);
+
+        // poll_ready 不取走消息，可以反复调用.
+        assert_eq!(receiver.poll_ready(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(receiver.poll_ready(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(Ok(5)));
+        assert_eq!(
+            receiver.poll_ready(&mut cx),
+            Poll::Ready(Err(RecvError::Disconnected))
+        );
+
+        let (sender, mut receiver) = channel::<i32>();
+        assert!(receiver.poll_recv(&mut cx).is_pending());
+        drop(sender);
+        assert_eq!(
+            receiver.poll_recv(&mut cx),
+            Poll::Ready(Err(RecvError::Disconnected))
+        );
+    }
+
+    #[test]
+    fn with_value_is_ready() {
+        let receiver = ready(String::from("done"));
+        assert!(receiver.is_ready());
+        assert_eq!(receiver.recv().as_deref(), Ok("done"));
+
+        let channel = Channel::with_value(3);
+        let (_sender, mut receiver) = channel.split_ref().unwrap();
+        assert_eq!(receiver.try_recv(), Ok(3));
+
+        let mut channel = Channel::with_value(4);
+        assert_eq!(channel.take(), Some(4));
+
+        // 从未被接收的消息由 Drop 释放.
+        let value = Arc::new(());
+        drop(Channel::with_value(value.clone()));
+        drop(ready(value.clone()));
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn try_split_refuses_unread_message() {
+        let mut channel = Channel::new();
+        let (sender, receiver) = channel.try_split().unwrap();
+        sender.send(1).unwrap();
+        drop(receiver);
+        assert!(channel.try_split().is_none());
+        // 通道保持原样，消息仍然可以取回.
+        assert_eq!(channel.take(), Some(1));
+
+        let (sender, receiver) = channel.try_split().unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(receiver.recv(), Ok(2));
+        // 消息已经被接收，可以再次 split.
+        assert!(channel.try_split().is_some());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn panicking_waker_does_not_leak() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        struct PanickingWaker;
+        impl Wake for PanickingWaker {
+            fn wake(self: Arc<Self>) {
+                panic!("waker panicked");
+            }
+        }
+
+        /// 被释放时设置标志.
+        struct Flag(Arc<AtomicBool>);
+        impl Drop for Flag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let waker = Arc::new(PanickingWaker).into();
+        let mut cx = Context::from_waker(&waker);
+        for receive in [true, false] {
+            let dropped = Arc::new(AtomicBool::new(false));
+            let (sender, mut receiver) = channel();
+            assert!(receiver.poll_recv(&mut cx).is_pending());
+            let flag = Flag(dropped.clone());
+            let result = panic::catch_unwind(AssertUnwindSafe(|| sender.send(flag)));
+            assert!(result.is_err());
+            // unpark 在消息进入 READY 之后才 panic，消息既没有丢失也没有泄漏.
+            assert!(receiver.is_ready());
+            if receive {
+                drop(receiver.try_recv().unwrap());
+            } else {
+                drop(receiver);
+            }
+            assert!(dropped.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn debug_value_prints_message() {
+        let mut channel = Channel::new();
+        assert_eq!(format!("{:?}", channel.debug_value()), "None");
+        let (sender, receiver) = channel.split();
+        sender.send("hi").unwrap();
+        drop(receiver);
+        assert_eq!(format!("{:?}", channel.debug_value()), r#"Some("hi")"#);
+        // 结构体本身的 Debug 不要求 T: Debug，也不打印消息.
+        assert!(!format!("{channel:?}").contains("hi"));
+    }
+
+    #[test]
+    fn take_does_not_double_drop() {
+        /// 被释放两次时 panic.
+        struct DropOnce(Arc<AtomicBool>);
+        impl Drop for DropOnce {
+            fn drop(&mut self) {
+                assert!(
+                    !self.0.swap(true, core::sync::atomic::Ordering::SeqCst),
+                    "dropped twice"
+                );
+            }
+        }
+
+        let dropped = Arc::new(AtomicBool::new(false));
+        let mut channel = Channel::new();
+        let (sender, receiver) = channel.split();
+        sender.send(DropOnce(dropped.clone())).unwrap();
+        drop(receiver);
+        let taken = channel.take().unwrap();
+        assert!(!dropped.load(core::sync::atomic::Ordering::SeqCst));
+        // 消息已经被移出，Channel 的 Drop 不会再释放它.
+        drop(channel);
+        assert!(!dropped.load(core::sync::atomic::Ordering::SeqCst));
+        drop(taken);
+        assert!(dropped.load(core::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn is_receiver_alive() {
+        let mut channel = Channel::<i32>::new();
+        let (sender, receiver) = channel.split();
+        let other = sender.clone();
+        assert!(sender.is_receiver_alive());
+        drop(receiver);
+        assert!(!sender.is_receiver_alive());
+        assert_eq!(sender.send(1), Err(SendError(1)));
+        assert!(!other.is_receiver_alive());
+        drop(other);
+
+        // 消息已经发送之后 Receiver 才被丢弃，状态保持 READY.
+        let (sender, receiver) = channel.split();
+        let other = sender.clone();
+        sender.send(2).unwrap();
+        drop(receiver);
+        assert!(other.is_receiver_alive());
+        drop(other);
+        assert_eq!(channel.take(), Some(2));
+    }
+
+    #[test]
+    fn on_ready_callback() {
+        // 消息已经到达: 在登记的线程上立即调用.
+        let (sender, receiver) = channel();
+        sender.send(1).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let here = thread::current().id();
+        receiver.on_ready(move |msg| tx.send((msg, thread::current().id())).unwrap());
+        assert_eq!(rx.try_recv().unwrap(), (1, here));
+
+        // 还没有到达: 由 send 在发送方的线程上调用.
+        let (sender, receiver) = channel();
+        let (tx, rx) = std::sync::mpsc::channel();
+        receiver.on_ready(move |msg: String| tx.send((msg, thread::current().id())).unwrap());
+        assert!(rx.try_recv().is_err());
+        let sender_thread = thread::spawn(move || {
+            sender.send(String::from("late")).unwrap();
+            thread::current().id()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(rx.recv().unwrap(), (String::from("late"), sender_thread));
+
+        // Sender 没有发送就被丢弃: 回调不会被调用，随通道一起被释放.
+        let (sender, receiver) = channel::<i32>();
+        let token = Arc::new(());
+        let held = token.clone();
+        receiver.on_ready(move |_| drop(held));
+        drop(sender);
+        assert_eq!(Arc::strong_count(&token), 1);
+    }
+
+    #[test]
+    fn resplit_drops_unread_message() {
+        let value = Arc::new(());
+        let mut channel = Channel::new();
+
+        // 发送之后两端都被丢弃(按两种顺序)，再次 split 时恰好释放一次未读的消息.
+        let (sender, receiver) = channel.split();
+        sender.send(value.clone()).unwrap();
+        drop(receiver);
+        let (sender, receiver) = channel.split();
+        assert_eq!(Arc::strong_count(&value), 1);
+        let other = sender.clone();
+        other.send(value.clone()).unwrap();
+        drop(sender);
+        drop(receiver);
+        let (sender, receiver) = channel.split();
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // 上一轮 on_ready 登记、从未被调用的回调同样被释放.
+        let held = value.clone();
+        receiver.on_ready(move |_| drop(held));
+        drop(sender);
+        assert_eq!(Arc::strong_count(&value), 2);
+        let (sender, receiver) = channel.split();
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // 新的一轮与新创建的通道一样.
+        assert!(sender.is_receiver_alive());
+        sender.send(value.clone()).unwrap();
+        assert!(receiver.recv().is_ok());
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn try_send_first_wins() {
+        let (sender, receiver) = channel();
+        let results: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let sender = sender.clone();
+                    s.spawn(move || sender.try_send(i))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let winners: Vec<_> = results.iter().filter(|r| r.is_ok()).collect();
+        assert_eq!(winners.len(), 1);
+        // 其余的线程都拿回了自己的消息.
+        let mut returned: Vec<_> = results
+            .into_iter()
+            .filter_map(|r| match r {
+                Err(TrySendError::Full(msg)) => Some(msg),
+                Ok(()) => None,
+                Err(TrySendError::Disconnected(_)) => unreachable!(),
+            })
+            .collect();
+        returned.push(receiver.recv().unwrap());
+        returned.sort();
+        assert_eq!(returned, (0..8).collect::<Vec<_>>());
+
+        let (sender, receiver) = channel();
+        drop(receiver);
+        assert!(matches!(
+            sender.try_send(1),
+            Err(TrySendError::Disconnected(1))
+        ));
+    }
+
+    /// 消息的三种结局，每种都只释放一次，也不会读取未初始化的 MaybeUninit. 用 cargo miri test 运行时，
+    /// 这里的每一次 assume_init_read / assume_init_drop 都会被检查.
+    #[test]
+    fn message_lifecycle() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+
+        // send 之后 recv: 由接收方释放.
+        let mut channel = Channel::new();
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            let msg = DropCounter::new(1, &dropped);
+            s.spawn(move || sender.send(msg).unwrap());
+            drop(receiver.recv().unwrap());
+        });
+        assert_eq!(drops(&dropped), 1);
+
+        // send 之后丢弃 Receiver 而不接收: 由 Channel 的 Drop 释放.
+        thread::scope(|s| {
+            let (sender, receiver) = channel.split();
+            let msg = DropCounter::new(2, &dropped);
+            s.spawn(move || sender.send(msg).unwrap());
+            s.spawn(move || drop(receiver));
+        });
+        assert_eq!(drops(&dropped), 1);
+        drop(channel);
+        assert_eq!(drops(&dropped), 2);
+
+        // 没有 send 就丢弃: 没有消息需要释放.
+        let mut channel = Channel::<DropCounter>::new();
+        drop(channel.split());
+        drop(channel);
+        let (sender, receiver) = crate::channel::<DropCounter>();
+        thread::spawn(move || drop(sender)).join().unwrap();
+        assert!(matches!(receiver.recv(), Err(RecvError::Disconnected)));
+        assert_eq!(drops(&dropped), 2);
+
+        // 堆上的通道: 最后一个 Arc 在另一个线程上被释放时，仍然只释放一次.
+        let (sender, receiver) = crate::channel();
+        sender.send(DropCounter::new(3, &dropped)).unwrap();
+        thread::spawn(move || drop(receiver)).join().unwrap();
+        assert_eq!(drops(&dropped), 3);
+    }
+}
+
+/// 用 loom 穷举一次 send 与一次 recv 之间所有可能的线程交错.
+/// loom 会检查 message 上的每一次访问是否都被 ready 的 Release/Acquire 正确地同步，
+/// 以及接收方是否可能错过唤醒而永远停放(表现为死锁).
+/// 接收方放在新线程中: Sender 唤醒的是 Receiver 所在的线程，loom 下这次 unpark
+/// 若落在主线程的 join 上会被当作非法的唤醒，所以主线程只负责发送和 join.
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+
+    use super::*;
+
+    #[test]
+    fn send_then_recv() {
+        loom::model(|| {
+            let (sender, receiver) = channel();
+            let handle = thread::spawn(move || receiver.recv());
+            sender.send(String::from("hello rustacean!")).unwrap();
+            assert_eq!(handle.join().unwrap().unwrap(), "hello rustacean!");
+        });
+    }
+
+    #[test]
+    fn try_recv_races_with_send() {
+        loom::model(|| {
+            let (sender, mut receiver) = channel();
+            let handle = thread::spawn(move || match receiver.try_recv() {
+                Ok(msg) => msg,
+                Err(TryRecvError::Empty) => receiver.recv().unwrap(),
+                Err(TryRecvError::Disconnected | TryRecvError::Cancelled) => unreachable!(),
+            });
+            sender.send(String::from("hello rustacean!")).unwrap();
+            assert_eq!(handle.join().unwrap(), "hello rustacean!");
+        });
+    }
+
+    #[test]
+    fn recv_disconnected() {
+        loom::model(|| {
+            let (sender, receiver) = channel::<String>();
+            let handle = thread::spawn(move || receiver.recv());
+            drop(sender);
+            assert_eq!(handle.join().unwrap(), Err(RecvError::Disconnected));
+        });
+    }
+
+    #[test]
+    fn send_races_with_receiver_drop() {
+        loom::model(|| {
+            let (sender, receiver) = channel();
+            let handle = thread::spawn(move || sender.send(String::from("hello rustacean!")));
+            drop(receiver);
+            // 无论谁先发生，消息要么交还给发送方，要么留在通道中由 Channel 的 Drop 释放.
+            if let Err(SendError(msg)) = handle.join().unwrap() {
+                assert_eq!(msg, "hello rustacean!");
+            }
+        });
+    }
+
+    #[test]
+    fn on_ready_races_with_send() {
+        loom::model(|| {
+            let (sender, receiver) = channel();
+            let calls = Arc::new(AtomicUsize::new(0));
+            let handle = thread::spawn(move || sender.send(5).unwrap());
+            let counter = calls.clone();
+            receiver.on_ready(move |msg| {
+                assert_eq!(msg, 5);
+                counter.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            });
+            handle.join().unwrap();
+            // 无论 send 与登记谁先发生，回调恰好被调用一次.
+            assert_eq!(calls.load(core::sync::atomic::Ordering::Relaxed), 1);
         });
     }
 }
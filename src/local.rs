@@ -0,0 +1,224 @@
+//! 单线程的 One-Shot channel，用于在同一个线程内传递不是 Send 的值(Rc 构成的图、原始指针等)，
+//! 例如在回调和注册它的代码之间，或者状态机的两个部分之间.
+//! 接口与 Channel 相同: send 和 recv 都消耗自身，每个通道只传递一条消息. 区别在于:
+//!     1.状态放在 Cell/RefCell 中，不使用原子操作，LocalChannel 既不是 Send 也不是 Sync；
+//!     2.不会停放线程: 在同一个线程上等待另一半发送必然死锁，所以 recv 在消息还没到达时
+//!       立即返回 TryRecvError::Empty，而不是阻塞.
+//! 错误类型与线程间的 channel 共用.
+
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::Deref;
+
+use crate::{SendError, TryRecvError};
+
+/// 创建一个放在 Rc 中的 LocalChannel，两端都不借用栈上的数据.
+pub fn channel<T>() -> (Sender<'static, T>, Receiver<'static, T>) {
+    let channel = Rc::new(LocalChannel::new());
+    (
+        Sender {
+            inner: LocalRef::Shared(channel.clone()),
+        },
+        Receiver {
+            inner: LocalRef::Shared(channel),
+        },
+    )
+}
+
+pub struct LocalChannel<T> {
+    message: RefCell<Option<T>>,
+    // sent : 是否已经发送过消息(消息可能已经被取走).
+    sent: Cell<bool>,
+    // disconnected : Sender 是否在没有发送消息的情况下被丢弃.
+    disconnected: Cell<bool>,
+    // closed : Receiver 是否已经被丢弃.
+    closed: Cell<bool>,
+}
+
+impl<T> LocalChannel<T> {
+    pub const fn new() -> Self {
+        Self {
+            message: RefCell::new(None),
+            sent: Cell::new(false),
+            disconnected: Cell::new(false),
+            closed: Cell::new(false),
+        }
+    }
+
+    /// 与 Channel::split 相同，通过独占借用保证只有一个 Sender 和一个 Receiver.
+    /// 上一轮没有被接收的消息在这里被丢弃.
+    pub fn split(&mut self) -> (Sender<'_, T>, Receiver<'_, T>) {
+        *self = Self::new();
+        (
+            Sender {
+                inner: LocalRef::Borrowed(self),
+            },
+            Receiver {
+                inner: LocalRef::Borrowed(self),
+            },
+        )
+    }
+}
+
+impl<T> Default for LocalChannel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for LocalChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalChannel")
+            .field("sent", &self.sent.get())
+            .field("disconnected", &self.disconnected.get())
+            .field("closed", &self.closed.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// 与 ChannelRef 相同，只是共享时用 Rc 而不是 Arc.
+enum LocalRef<'a, T> {
+    Borrowed(&'a LocalChannel<T>),
+    Shared(Rc<LocalChannel<T>>),
+}
+
+impl<T> Deref for LocalRef<'_, T> {
+    type Target = LocalChannel<T>;
+
+    fn deref(&self) -> &LocalChannel<T> {
+        match self {
+            LocalRef::Borrowed(channel) => channel,
+            LocalRef::Shared(channel) => channel,
+        }
+    }
+}
+
+pub struct Sender<'a, T> {
+    inner: LocalRef<'a, T>,
+}
+
+impl<T> Sender<'_, T> {
+    /// 发送消息. Receiver 已经被丢弃时通过 SendError 交还消息.
+    pub fn send(self, msg: T) -> Result<(), SendError<T>> {
+        if self.inner.closed.get() {
+            return Err(SendError(msg));
+        }
+        *self.inner.message.borrow_mut() = Some(msg);
+        self.inner.sent.set(true);
+        Ok(())
+    }
+}
+
+impl<T> Drop for Sender<'_, T> {
+    fn drop(&mut self) {
+        if !self.inner.sent.get() {
+            self.inner.disconnected.set(true);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("receiver_alive", &!self.inner.closed.get())
+            .finish()
+    }
+}
+
+pub struct Receiver<'a, T> {
+    inner: LocalRef<'a, T>,
+}
+
+impl<T> Receiver<'_, T> {
+    /// 消息是否已经到达并且还没有被取走.
+    pub fn is_ready(&self) -> bool {
+        self.inner.message.borrow().is_some()
+    }
+
+    /// 取走消息. 消息还没到达时返回 Empty，Sender 没有发送就被丢弃时返回 Disconnected.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(msg) = self.inner.message.borrow_mut().take() {
+            return Ok(msg);
+        }
+        if self.inner.disconnected.get() {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// 与 try_recv 相同，但消耗 Receiver. 不会阻塞: 消息还没到达时返回 Empty，
+    /// 之后再也无法取得这条消息，所以只应在确认 Sender 已经发送(或已经被丢弃)之后调用.
+    pub fn recv(mut self) -> Result<T, TryRecvError> {
+        self.try_recv()
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    fn drop(&mut self) {
+        self.inner.closed.set(true);
+    }
+}
+
+impl<T> fmt::Debug for Receiver<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// 若 T 实现了 $trait，AmbiguousIfImpl 对 T 有两个实现，类型推断失败，编译报错.
+    macro_rules! assert_not_impl {
+        ($ty:ty: $trait:path) => {{
+            trait AmbiguousIfImpl<A> {
+                fn check() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            impl<T: ?Sized + $trait> AmbiguousIfImpl<u8> for T {}
+            <$ty as AmbiguousIfImpl<_>>::check();
+        }};
+    }
+
+    #[test]
+    fn not_send_or_sync() {
+        assert_not_impl!(LocalChannel<i32>: Sync);
+        assert_not_impl!(Sender<'static, i32>: Send);
+        assert_not_impl!(Sender<'static, i32>: Sync);
+        assert_not_impl!(Receiver<'static, i32>: Send);
+        assert_not_impl!(Receiver<'static, i32>: Sync);
+    }
+
+    #[test]
+    fn send_and_recv_rc() {
+        let value = Rc::new(5);
+        let (sender, mut receiver) = channel();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(value.clone()).unwrap();
+        assert!(receiver.is_ready());
+        assert_eq!(*receiver.recv().unwrap(), 5);
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        let mut channel = LocalChannel::<i32>::new();
+        let (sender, receiver) = channel.split();
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_after_receiver_drop() {
+        let (sender, receiver) = channel();
+        drop(receiver);
+        assert_eq!(
+            sender.send(Rc::new(1)).unwrap_err().into_inner(),
+            Rc::new(1)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! 可选的运行时统计(metrics feature): 每个 Channel 记录成功发送的次数、成功接收的次数，
+//! 以及阻塞接收停放线程的次数(每次 park / park_timeout 计一次，包括虚假唤醒之后的再次停放)，
+//! 监控面板可以通过 Channel::metrics 得到的 Arc 随时读取.
+//! 计数器在第一次被用到时才分配，复用同一个 Channel(split / reset)时继续累加.
+//! 关闭 feature 时 record! 展开为空，Channel 中也没有这个字段，不产生任何开销.
+
+#[cfg(feature = "metrics")]
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// 一个通道的累计统计. 各计数器用 Relaxed 更新和读取，只是某一时刻的近似值，
+/// 不能用来与通道中的消息同步.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub struct ChannelMetrics {
+    pub(crate) sends: AtomicU64,
+    pub(crate) recvs: AtomicU64,
+    pub(crate) parks: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl ChannelMetrics {
+    /// 成功写入通道的消息数(send / send_with / send_replace / try_send).
+    pub fn sends(&self) -> u64 {
+        self.sends.load(Ordering::Relaxed)
+    }
+
+    /// 被取走的消息数(try_recv / recv 以及 on_ready 的回调).
+    pub fn recvs(&self) -> u64 {
+        self.recvs.load(Ordering::Relaxed)
+    }
+
+    /// 阻塞接收停放线程的次数.
+    pub fn parks(&self) -> u64 {
+        self.parks.load(Ordering::Relaxed)
+    }
+}
+
+/// 给 channel(一个 &Channel<T>)的某个计数器加一，例如 record!(self.inner, sends).
+macro_rules! record {
+    ($channel:expr, $counter:ident) => {
+        #[cfg(feature = "metrics")]
+        {
+            $channel
+                .metrics_ref()
+                .$counter
+                .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        }
+    };
+}
+
+pub(crate) use record;
+
+#[cfg(all(test, feature = "metrics", not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{Channel, Park};
+
+    #[test]
+    fn counts_sends_recvs_and_parks() {
+        let mut channel = Channel::<u32>::with_wait_strategy(&Park);
+        let metrics = channel.metrics();
+        for i in 0..3 {
+            let (sender, receiver) = channel.split();
+            sender.send(i).unwrap();
+            assert_eq!(receiver.recv(), Ok(i));
+        }
+        // reset 之后继续累加，消息已经到达时 recv 不会停放.
+        assert_eq!(
+            (metrics.sends(), metrics.recvs(), metrics.parks()),
+            (3, 3, 0)
+        );
+
+        let (sender, receiver) = channel.split();
+        thread::scope(|s| {
+            s.spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                sender.send(7).unwrap();
+            });
+            assert_eq!(receiver.recv(), Ok(7));
+        });
+        assert_eq!((metrics.sends(), metrics.recvs()), (4, 4));
+        assert!(metrics.parks() >= 1);
+
+        // 失败的 send 不计数.
+        let (sender, receiver) = crate::channel::<u32>();
+        let metrics = sender.metrics();
+        drop(receiver);
+        assert!(sender.send(1).is_err());
+        assert_eq!(metrics.sends(), 0);
+    }
+}
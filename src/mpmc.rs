@@ -0,0 +1,253 @@
+//! 无界的 MPMC channel: Sender 和 Receiver 都可以 clone，适合多个工作线程竞争同一个任务队列.
+//! 每条消息只会被其中一个 Receiver 取走. 实现是 Mutex<VecDeque<T>> 加一个 Condvar:
+//!     1.队列、存活的 Sender/Receiver 数量和正在等待的 Receiver 数量都放在同一把锁里，
+//!       send 与 recv 看到的总是一致的快照；
+//!     2.send 在锁内放入消息，只有 waiting 不为 0 时才 notify_one. 等待方在锁内检查队列、
+//!       登记 waiting 再 wait，所以要么已经看到这条消息，要么已经在 wait 中，不会错过唤醒.
+//!       每条消息唤醒一个等待者: 被唤醒的 Receiver 发现消息已被另一个没有等待的 Receiver 抢走时，
+//!       只是重新等待，而抢走消息的那个 Receiver 也消耗了这条消息，不会有消息在等待者都睡着时留在队列里；
+//!     3.最后一个 Sender 被丢弃时 notify_all，所有等待者取完剩下的消息之后返回 Disconnected.
+//!       最后一个 Receiver 被丢弃时，队列中的消息随之释放，之后的 send 通过 SendError 交还消息.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{RecvError, SendError, TryRecvError};
+
+/// 创建一个 MPMC channel，两端都可以 clone 并发送到其他线程.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            items: VecDeque::new(),
+            senders: 1,
+            receivers: 1,
+            waiting: 0,
+        }),
+        available: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+struct Shared<T> {
+    queue: Mutex<Queue<T>>,
+    // available : 队列中有新消息，或者最后一个 Sender 已经被丢弃.
+    available: Condvar,
+}
+
+struct Queue<T> {
+    items: VecDeque<T>,
+    senders: usize,
+    receivers: usize,
+    // waiting : 正在 available 上等待的 Receiver 数量，为 0 时 send 不需要 notify.
+    waiting: usize,
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 把消息放入队列，唤醒一个等待中的 Receiver. 所有 Receiver 都已被丢弃时通过 SendError 交还消息.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.receivers == 0 {
+            return Err(SendError(msg));
+        }
+        queue.items.push_back(msg);
+        let notify = queue.waiting != 0;
+        drop(queue);
+        if notify {
+            self.shared.available.notify_one();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.queue.lock().unwrap().senders += 1;
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 最后一个 Sender 被丢弃时唤醒所有等待者，取完剩下的消息之后它们会收到 Disconnected.
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.senders -= 1;
+        if queue.senders == 0 {
+            drop(queue);
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+/// Receiver 可以被 clone，所有 Receiver 共享同一个队列，竞争其中的消息.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// 非阻塞地取出队首的消息.
+    /// 队列为空时，如果所有 Sender 都已被丢弃返回 Disconnected，否则返回 Empty.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        match queue.items.pop_front() {
+            Some(msg) => Ok(msg),
+            None if queue.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// 阻塞直到取得一条消息. 所有 Sender 都被丢弃并且队列已空时返回 Disconnected.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if let Some(msg) = queue.items.pop_front() {
+                return Ok(msg);
+            }
+            if queue.senders == 0 {
+                return Err(RecvError::Disconnected);
+            }
+            queue.waiting += 1;
+            queue = self.shared.available.wait(queue).unwrap();
+            queue.waiting -= 1;
+        }
+    }
+
+    /// 队列中的消息数，只是某一时刻的快照.
+    pub fn len(&self) -> usize {
+        self.shared.queue.lock().unwrap().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.queue.lock().unwrap().receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    /// 最后一个 Receiver 被丢弃时释放队列中剩下的消息. 消息在锁外释放，它们的 Drop 可以再使用这个通道.
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.receivers -= 1;
+        if queue.receivers == 0 {
+            let items = std::mem::take(&mut queue.items);
+            drop(queue);
+            drop(items);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn each_item_consumed_once() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        // Miri 下执行太慢，只发送一小部分.
+        const ITEMS: usize = if cfg!(miri) { 1_000 } else { 100_000 };
+
+        let (sender, receiver) = channel();
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for i in (p..ITEMS).step_by(PRODUCERS) {
+                        sender.send(i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let consumers: Vec<_> = (0..CONSUMERS)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    let mut received = Vec::new();
+                    while let Ok(i) = receiver.recv() {
+                        received.push(i);
+                    }
+                    received
+                })
+            })
+            .collect();
+        drop(receiver);
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let mut seen = vec![false; ITEMS];
+        for consumer in consumers {
+            for i in consumer.join().unwrap() {
+                assert!(!seen[i], "{i} received twice");
+                seen[i] = true;
+            }
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+
+    #[test]
+    fn single_item_wakes_a_parked_receiver() {
+        let (sender, receiver) = channel();
+        let got = Arc::new(AtomicBool::new(false));
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let receiver = receiver.clone();
+                let got = got.clone();
+                thread::spawn(move || {
+                    if receiver.recv().is_ok() {
+                        assert!(!got.swap(true, Ordering::Relaxed));
+                    }
+                })
+            })
+            .collect();
+        // 等所有 Receiver 都停放之后再发送，只有一个会收到消息.
+        while sender.shared.queue.lock().unwrap().waiting != 3 {
+            thread::yield_now();
+        }
+        sender.send(7).unwrap();
+        while !got.load(Ordering::Relaxed) {
+            thread::yield_now();
+        }
+        // 剩下的两个等待者在 Sender 被丢弃之后收到 Disconnected.
+        drop(sender);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_after_receivers_dropped() {
+        let (sender, receiver) = channel();
+        let other = receiver.clone();
+        sender.send(String::from("queued")).unwrap();
+        drop(receiver);
+        // 还有一个 Receiver，消息仍然能被取走.
+        assert_eq!(other.try_recv().as_deref(), Ok("queued"));
+        assert_eq!(other.try_recv(), Err(TryRecvError::Empty));
+        sender.send(String::from("dropped with queue")).unwrap();
+        drop(other);
+        assert_eq!(sender.send(String::from("late")).unwrap_err().0, "late");
+    }
+}
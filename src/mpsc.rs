@@ -0,0 +1,405 @@
+//! 无界的 MPSC channel: 任意多个 Sender 可以并发地 send 而不会阻塞，只有一个 Receiver 接收.
+//! 消息放在一个无锁的侵入式链表队列中(Vyukov 的 MPSC 队列，Michael–Scott 队列的单消费者简化版):
+//!     1.队列总是以一个不带消息的哨兵节点开头. tail 指向哨兵，只由 Receiver 访问；
+//!       head 指向最后一个节点，由所有 Sender 通过 swap 竞争；
+//!     2.send 分配一个新节点，swap 把它设为 head，再把旧 head 的 next 指向它.
+//!       两步之间队列暂时"断开"，此时 Receiver 看到 tail.next 为空但 head != tail，让出执行权后重试；
+//!     3.recv 在队列为空时停放，每次 send 之后都会唤醒登记在 waiter 中的 Receiver.
+//!
+//! 节点的回收不需要 epoch 或 hazard pointer: 只有 Receiver 会释放节点，而且只释放已经越过的哨兵.
+//! Sender 唯一会解引用的节点是它 swap 得到的旧 head，在这个 Sender 写入旧 head 的 next 之前，
+//! Receiver 无法越过旧 head(它的 next 仍为空)，所以旧 head 不会在被访问时被释放.
+//! 也不存在 ABA 问题: head 只被 swap，从不被 compare_exchange.
+
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::Ordering;
+
+use crate::cache_padded::CachePadded;
+use crate::sync::{thread, Arc, AtomicBool, AtomicPtr, AtomicUsize};
+use crate::waiter::Waiter;
+use crate::{RecvError, SendError, TryRecvError};
+
+/// 创建一个无界的 MPSC channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let stub = Node::new(None);
+    let queue = Arc::new(Queue {
+        head: CachePadded::new(AtomicPtr::new(stub)),
+        tail: CachePadded::new(crate::sync::UnsafeCell::new(stub)),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        waiter: Waiter::new(),
+    });
+    (
+        Sender {
+            queue: queue.clone(),
+        },
+        Receiver {
+            queue,
+            _not_send: PhantomData,
+        },
+    )
+}
+
+struct Node<T> {
+    next: AtomicPtr<Node<T>>,
+    // 哨兵节点为 None. 由 send 在发布节点之前写入，由 Receiver 在越过哨兵之后取走.
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn new(value: Option<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            value,
+        }))
+    }
+}
+
+/// pop 的结果.
+enum Pop<T> {
+    Data(T),
+    Empty,
+    // 某个 Sender 已经 swap 了 head，但还没有链接旧 head 的 next. 很快就会有数据.
+    Inconsistent,
+}
+
+struct Queue<T> {
+    // head 与 tail 分别被 Sender 和 Receiver 频繁写入，放在不同的缓存行.
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    tail: CachePadded<crate::sync::UnsafeCell<*mut Node<T>>>,
+    // senders : 存活的 Sender 数量.
+    senders: AtomicUsize,
+    // closed : Receiver 是否已经被丢弃.
+    closed: AtomicBool,
+    waiter: Waiter,
+}
+
+// 节点通过原始指针共享，消息只会从 Sender 移动到 Receiver，所以只要求 T: Send.
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    fn push(&self, value: T) {
+        let node = Node::new(Some(value));
+        // AcqRel: Release 发布 node 的内容给之后 swap 到它的 Sender，Acquire 得到 prev 的所有权.
+        let prev = self.head.swap(node, Ordering::AcqRel);
+        // 安全性: prev 的 next 仍为空，Receiver 无法越过并释放它，见模块文档.
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+
+    /// 只能由唯一的 Receiver 调用.
+    unsafe fn pop(&self) -> Pop<T> {
+        self.tail.with_mut(|tail| {
+            let stub = *tail;
+            let next = (*stub).next.load(Ordering::Acquire);
+            if next.is_null() {
+                return if self.head.load(Ordering::Acquire) == stub {
+                    Pop::Empty
+                } else {
+                    Pop::Inconsistent
+                };
+            }
+            // next 成为新的哨兵，旧哨兵已经没有任何 Sender 会访问.
+            *tail = next;
+            let value = (*next)
+                .value
+                .take()
+                .expect("non-stub node carries a message");
+            drop(Box::from_raw(stub));
+            Pop::Data(value)
+        })
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    /// 此时 Sender 和 Receiver 都已不存在，释放剩下的所有节点(以及未被接收的消息).
+    fn drop(&mut self) {
+        let mut node = self.tail.with_mut(|tail| unsafe { *tail });
+        while !node.is_null() {
+            let next = unsafe { (*node).next.load(Ordering::Relaxed) };
+            drop(unsafe { Box::from_raw(node) });
+            node = next;
+        }
+    }
+}
+
+/// Sender 可以被 clone 并发送到其他线程，send 从不阻塞.
+pub struct Sender<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 把消息放入队列尾部并唤醒 Receiver. Receiver 已经被丢弃时通过 SendError 交还消息.
+    /// 与 One-Shot channel 相同，这只是尽力而为: 与 Receiver 的 drop 并发的消息由队列的 Drop 释放.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        if self.queue.closed.load(Ordering::Relaxed) {
+            return Err(SendError(msg));
+        }
+        self.queue.push(msg);
+        self.queue.waiter.unpark();
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.queue.senders.fetch_add(1, Ordering::Relaxed);
+        Sender {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 最后一个 Sender 被丢弃时唤醒 Receiver，读完剩下的消息之后它会收到 Disconnected.
+    fn drop(&mut self) {
+        // Release: 之前 push 的消息对看到 senders == 0 的 Receiver 可见.
+        if self.queue.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.queue.waiter.unpark();
+        }
+    }
+}
+
+/// Receiver 不是 Send: 队列只允许一个消费者，而且它固定在创建通道的线程上.
+/// 因此 Waiter 中登记的总是同一个线程.
+pub struct Receiver<T> {
+    queue: Arc<Queue<T>>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> Receiver<T> {
+    /// 非阻塞地取出最早的一条消息.
+    /// 队列为空时，如果所有 Sender 都已被丢弃返回 Disconnected，否则返回 Empty.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            // 安全性: Receiver 不是 Send/Sync 也不能 clone，&mut self 保证只有一个消费者.
+            match unsafe { self.queue.pop() } {
+                Pop::Data(msg) => return Ok(msg),
+                Pop::Inconsistent => thread::yield_now(),
+                Pop::Empty => {
+                    if self.queue.senders.load(Ordering::Acquire) != 0 {
+                        return Err(TryRecvError::Empty);
+                    }
+                    // 最后一个 Sender 在 drop 之前 push 的消息此时一定可见，再检查一次.
+                    return match unsafe { self.queue.pop() } {
+                        Pop::Data(msg) => Ok(msg),
+                        _ => Err(TryRecvError::Disconnected),
+                    };
+                }
+            }
+        }
+    }
+
+    /// 阻塞直到有消息. 所有 Sender 都被丢弃并且队列已空时返回 Disconnected.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut registered = false;
+        let result = loop {
+            match self.try_recv() {
+                Ok(msg) => break Ok(msg),
+                Err(TryRecvError::Empty) => {}
+                Err(_) => break Err(RecvError::Disconnected),
+            }
+            // 先登记再检查一次，登记之前到达的消息不会错过唤醒.
+            if registered {
+                thread::park();
+            } else {
+                self.queue.waiter.register();
+                registered = true;
+            }
+        };
+        if registered {
+            self.queue.waiter.unregister();
+        }
+        result
+    }
+
+    /// 阻塞直到至少有一条消息，然后不再阻塞地取出队列中现有的消息，最多 max 条，追加到 buf 末尾，
+    /// 返回取出的条数. 只有第一条消息可能需要停放和登记 waiter.
+    /// 返回 0 表示所有 Sender 都已被丢弃并且队列为空(或者 max 为 0).
+    pub fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        if max == 0 {
+            return 0;
+        }
+        let Ok(first) = self.recv() else {
+            return 0;
+        };
+        buf.push(first);
+        let mut count = 1;
+        while count < max {
+            match self.try_recv() {
+                Ok(msg) => buf.push(msg),
+                Err(_) => break,
+            }
+            count += 1;
+        }
+        count
+    }
+}
+
+/// 依次产出队列中的消息，所有 Sender 都被丢弃并且队列已空时结束.
+/// 每次返回 Pending 之前都重新登记 Waker(任务可能已经换了)，登记之后再检查一次队列，
+/// 与登记同时到达的消息不会错过唤醒.
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<T>> {
+        use core::task::Poll;
+
+        let this = self.get_mut();
+        match this.try_recv() {
+            Ok(msg) => return Poll::Ready(Some(msg)),
+            Err(TryRecvError::Empty) => {}
+            Err(_) => return Poll::Ready(None),
+        }
+        this.queue.waiter.register_waker(cx.waker());
+        let result = match this.try_recv() {
+            Ok(msg) => Some(msg),
+            Err(TryRecvError::Empty) => return Poll::Pending,
+            Err(_) => None,
+        };
+        this.queue.waiter.unregister();
+        Poll::Ready(result)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.queue.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn many_producers() {
+        let (sender, mut receiver) = channel();
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        sender.send(t * 100 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut received = Vec::new();
+        while let Ok(msg) = receiver.recv() {
+            received.push(msg);
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        // 同一个 Sender 的消息保持发送顺序.
+        for t in 0..4 {
+            let own: Vec<_> = received.iter().filter(|&&m| m / 100 == t).collect();
+            assert!(own.windows(2).all(|w| w[0] < w[1]));
+        }
+        received.sort();
+        assert_eq!(received, (0..400).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stress_fifo_per_producer() {
+        const PRODUCERS: usize = 16;
+        const PER_PRODUCER: usize = 2_000;
+        let (sender, mut receiver) = channel();
+        let handles: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for seq in 0..PER_PRODUCER {
+                        sender.send((p, seq)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        // 每个 Sender 的消息按发送顺序、不重不漏地到达.
+        let mut next = [0; PRODUCERS];
+        while let Ok((p, seq)) = receiver.recv() {
+            assert_eq!(seq, next[p], "producer {p} out of order");
+            next[p] += 1;
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(next, [PER_PRODUCER; PRODUCERS]);
+    }
+
+    #[test]
+    fn try_recv_and_disconnect() {
+        let (sender, mut receiver) = channel();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn recv_many_drains_queue() {
+        let (sender, mut receiver) = channel();
+        for i in 0..5 {
+            sender.send(i).unwrap();
+        }
+        let mut buf = Vec::new();
+        assert_eq!(receiver.recv_many(&mut buf, 3), 3);
+        assert_eq!(receiver.recv_many(&mut buf, 10), 2);
+        assert_eq!(buf, [0, 1, 2, 3, 4]);
+        drop(sender);
+        assert_eq!(receiver.recv_many(&mut buf, 10), 0);
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (sender, receiver) = channel();
+        sender.send(String::from("left in queue")).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send(String::from("hello")).unwrap_err().0, "hello");
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+
+    use super::*;
+
+    #[test]
+    fn two_producers() {
+        loom::model(|| {
+            let (sender, mut receiver) = channel();
+            let other = sender.clone();
+            let handle = thread::spawn(move || other.send(1).unwrap());
+            sender.send(2).unwrap();
+            drop(sender);
+            // 与另一个 Sender 并发地取一次，可能遇到队列暂时断开的状态.
+            let mut received = Vec::new();
+            while let Ok(msg) = receiver.try_recv() {
+                received.push(msg);
+            }
+            handle.join().unwrap();
+            // Receiver 不是 Send，只能在主线程上用不会停放的 try_recv 收取，
+            // 避免 Sender 的 unpark 落在 join 上(见 lib.rs 中的 loom_test).
+            while let Ok(msg) = receiver.try_recv() {
+                received.push(msg);
+            }
+            received.sort();
+            assert_eq!(received, [1, 2]);
+        });
+    }
+}
@@ -0,0 +1,180 @@
+//! 广播式的 One-Shot channel: 一个 Sender 只发送一条消息，任意多个 Receiver 都能收到它.
+//! 与 One-Shot channel 不同，消息不会在第一次接收时被移出:
+//!     1.消息放在 Arc 中由 Mutex 保护的 State 里，每次 recv / try_recv 都 clone 一份交给调用方，
+//!       所以要求 T: Clone. 消息本身很大时，可以发送 Arc<T>，每次 clone 只增加一次引用计数；
+//!     2.Receiver 可以被 clone，State 中记录存活的 Receiver 数量. 最后一个 Receiver 被丢弃时消息随之释放，
+//!       此后的 send 通过 SendError 交还消息；
+//!     3.等待的 Receiver 数量不定，send 和 Sender 的 Drop 用 Condvar 的 notify_all 唤醒所有等待者.
+//! 多条消息的广播见 broadcast 模块.
+
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{RecvError, SendError, TryRecvError};
+
+/// 创建一个广播式的 One-Shot channel. 在 crate 根部以 broadcast_once 的名字导出.
+pub fn channel<T: Clone>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: Value::Empty,
+            receivers: 1,
+        }),
+        ready: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    // ready : 消息已经到达，或者 Sender 没有发送就被丢弃.
+    ready: Condvar,
+}
+
+struct State<T> {
+    value: Value<T>,
+    receivers: usize,
+}
+
+enum Value<T> {
+    Empty,
+    Ready(T),
+    // Disconnected : Sender 没有发送就被丢弃，或者最后一个 Receiver 已经被丢弃.
+    Disconnected,
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 保存消息并唤醒所有等待中的 Receiver. 所有 Receiver 都已被丢弃时通过 SendError 交还消息.
+    pub fn send(self, msg: T) -> Result<(), SendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.receivers == 0 {
+            return Err(SendError(msg));
+        }
+        state.value = Value::Ready(msg);
+        drop(state);
+        self.shared.ready.notify_all();
+        Ok(())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 没有发送就被丢弃时，唤醒所有等待者，让它们的 recv 返回 Disconnected.
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Value::Empty = state.value {
+            state.value = Value::Disconnected;
+            drop(state);
+            self.shared.ready.notify_all();
+        }
+    }
+}
+
+/// Receiver 可以被 clone，每个 Receiver 都能收到同一条消息的一份 clone.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone> Receiver<T> {
+    /// 非阻塞地读取消息的一份 clone. 消息留在通道中，之后的读取仍然会得到它.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match &self.shared.state.lock().unwrap().value {
+            Value::Empty => Err(TryRecvError::Empty),
+            Value::Ready(msg) => Ok(msg.clone()),
+            Value::Disconnected => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    /// 阻塞直到消息到达，返回它的一份 clone. Sender 没有发送就被丢弃时返回 Disconnected.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            match &state.value {
+                Value::Empty => state = self.shared.ready.wait(state).unwrap(),
+                Value::Ready(msg) => return Ok(msg.clone()),
+                Value::Disconnected => return Err(RecvError::Disconnected),
+            }
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    /// 最后一个 Receiver 被丢弃时释放消息. 消息在锁外释放，它的 Drop 可以再使用这个通道.
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            let value = std::mem::replace(&mut state.value, Value::Disconnected);
+            drop(state);
+            drop(value);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn every_receiver_gets_a_clone() {
+        let (sender, receiver) = channel();
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || receiver.recv())
+            })
+            .collect();
+        sender.send(String::from("hello rustacean!")).unwrap();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().as_deref(), Ok("hello rustacean!"));
+        }
+        // 消息在最后一个 Receiver 被丢弃之前一直留在通道中.
+        assert_eq!(receiver.try_recv().as_deref(), Ok("hello rustacean!"));
+        assert_eq!(receiver.clone().recv().as_deref(), Ok("hello rustacean!"));
+    }
+
+    #[test]
+    fn dropped_with_last_receiver() {
+        let value = Arc::new(());
+        let (sender, receiver) = channel();
+        let other = receiver.clone();
+        sender.send(value.clone()).unwrap();
+        drop(receiver);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(other);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // 没有 Receiver 时 send 交还消息.
+        let (sender, receiver) = channel();
+        drop(receiver);
+        assert_eq!(sender.send(1).unwrap_err().0, 1);
+    }
+
+    #[test]
+    fn sender_dropped_without_send() {
+        let (sender, receiver) = crate::broadcast_once::<i32>();
+        let other = receiver.clone();
+        let handle = thread::spawn(move || other.recv());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        drop(sender);
+        assert_eq!(handle.join().unwrap(), Err(RecvError::Disconnected));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+}
@@ -0,0 +1,69 @@
+//! 把两个 One-Shot channel 首尾相连，组成处理管线的一级: 返回的闭包阻塞接收上游的消息，
+//! (经过 f 变换之后)转发给下游. 闭包可以交给 thread::spawn 或线程池执行，也可以直接调用.
+//! 上游没有消息时，下游看到的结果与上游相同:
+//!     1.上游的 Sender 调用了 cancel，下游的 Sender 同样 cancel，接收方收到 Cancelled；
+//!     2.上游的 Sender 没有发送就被丢弃，下游的 Sender 随之被丢弃，接收方收到 Disconnected.
+//! 下游的 Receiver 已经被丢弃时，转发的消息被直接丢弃，没有人会再需要它.
+
+use crate::{Receiver, RecvError, Sender};
+
+/// 把 receiver 收到的消息原样转发给 sender.
+pub fn pipe<'a, T: 'a>(receiver: Receiver<'a, T>, sender: Sender<'a, T>) -> impl FnOnce() + 'a {
+    map_pipe(receiver, |msg| msg, sender)
+}
+
+/// 与 pipe 相同，但转发的是 f(msg). f 在执行闭包的线程上调用，并且只会在消息到达时被调用一次.
+pub fn map_pipe<'a, A: 'a, B: 'a>(
+    receiver: Receiver<'a, A>,
+    f: impl FnOnce(A) -> B + 'a,
+    sender: Sender<'a, B>,
+) -> impl FnOnce() + 'a {
+    move || match receiver.recv() {
+        Ok(msg) => {
+            let _ = sender.send_with(|| f(msg));
+        }
+        Err(RecvError::Cancelled) => sender.cancel(),
+        Err(RecvError::Disconnected) => drop(sender),
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::channel;
+
+    #[test]
+    fn two_stage_pipeline() {
+        let (input, first) = channel::<u32>();
+        let (middle, second) = channel();
+        let (output, result) = channel();
+        let stages = [
+            thread::spawn(map_pipe(first, |n| n * 2, middle)),
+            thread::spawn(map_pipe(second, |n: u32| n.to_string(), output)),
+        ];
+        input.send(21).unwrap();
+        assert_eq!(result.recv().as_deref(), Ok("42"));
+        for stage in stages {
+            stage.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn propagates_cancel_and_disconnect() {
+        let (input, upstream) = channel::<i32>();
+        let (downstream, result) = channel();
+        let stage = thread::spawn(pipe(upstream, downstream));
+        input.cancel();
+        stage.join().unwrap();
+        assert_eq!(result.recv(), Err(RecvError::Cancelled));
+
+        let (input, upstream) = channel::<i32>();
+        let (downstream, result) = channel();
+        let stage = pipe(upstream, downstream);
+        drop(input);
+        stage();
+        assert_eq!(result.recv(), Err(RecvError::Disconnected));
+    }
+}
@@ -0,0 +1,247 @@
+//! 按优先级出队的 MPSC channel，例如任务调度器中先处理更紧急的任务.
+//! 消息放在 Mutex<BinaryHeap<T>> 中，recv 总是取出当前最大的消息:
+//!     1.send 在锁内把消息放入堆中，然后唤醒登记在 waiter 中的 Receiver；
+//!     2.recv 在堆为空时停放，与 mpsc 模块相同，先登记再检查一次，不会错过唤醒.
+//! 消息本身不便实现 Ord 时，可以用 Prioritized 把它和一个优先级放在一起，
+//! 再通过 Sender::send_with_priority 发送. 优先级相同的消息之间的顺序不做保证.
+
+use alloc::collections::BinaryHeap;
+use core::cmp::{Ordering as CmpOrdering, Reverse};
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+use crate::sync::{thread, Arc, AtomicBool, AtomicUsize, Mutex};
+use crate::waiter::Waiter;
+use crate::{RecvError, SendError, TryRecvError};
+
+/// 创建一个按 T 的 Ord 出队(最大的先出)的 priority channel.
+pub fn channel<T: Ord>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        heap: Mutex::new(BinaryHeap::new()),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        waiter: Waiter::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+struct Shared<T> {
+    heap: Mutex<BinaryHeap<T>>,
+    // senders : 存活的 Sender 数量.
+    senders: AtomicUsize,
+    // closed : Receiver 是否已经被丢弃.
+    closed: AtomicBool,
+    waiter: Waiter,
+}
+
+/// 带优先级的消息. 只按 priority 比较，msg 不需要实现 Ord.
+/// 顺序是 Reverse(priority): priority 越小越先被接收，例如把截止时间或 nice 值作为优先级.
+pub struct Prioritized<P, T> {
+    pub priority: P,
+    pub msg: T,
+}
+
+impl<P, T> Prioritized<P, T> {
+    pub fn into_inner(self) -> T {
+        self.msg
+    }
+}
+
+impl<P: Ord, T> Ord for Prioritized<P, T> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        Reverse(&self.priority).cmp(&Reverse(&other.priority))
+    }
+}
+
+impl<P: Ord, T> PartialOrd for Prioritized<P, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Ord, T> PartialEq for Prioritized<P, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<P: Ord, T> Eq for Prioritized<P, T> {}
+
+impl<P: fmt::Debug, T: fmt::Debug> fmt::Debug for Prioritized<P, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prioritized")
+            .field("priority", &self.priority)
+            .field("msg", &self.msg)
+            .finish()
+    }
+}
+
+/// Sender 可以被 clone 并发送到其他线程.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Sender<T> {
+    /// 把消息放入堆中并唤醒 Receiver. Receiver 已经被丢弃时通过 SendError 交还消息.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        if self.shared.closed.load(Ordering::Relaxed) {
+            return Err(SendError(msg));
+        }
+        self.shared.heap.lock().unwrap().push(msg);
+        self.shared.waiter.unpark();
+        Ok(())
+    }
+}
+
+impl<P: Ord, T> Sender<Prioritized<P, T>> {
+    /// 以 priority 发送 msg，priority 越小越先被接收. 失败时交还的是包装后的 Prioritized.
+    pub fn send_with_priority(
+        &self,
+        msg: T,
+        priority: P,
+    ) -> Result<(), SendError<Prioritized<P, T>>> {
+        self.send(Prioritized { priority, msg })
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 最后一个 Sender 被丢弃时唤醒 Receiver，取完剩下的消息之后它会收到 Disconnected.
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.waiter.unpark();
+        }
+    }
+}
+
+/// 只有一个 Receiver，不能 clone.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Receiver<T> {
+    /// 非阻塞地取出当前最大的消息.
+    /// 堆为空时，如果所有 Sender 都已被丢弃返回 Disconnected，否则返回 Empty.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(msg) = self.shared.heap.lock().unwrap().pop() {
+            return Ok(msg);
+        }
+        if self.shared.senders.load(Ordering::Acquire) != 0 {
+            return Err(TryRecvError::Empty);
+        }
+        // 最后一个 Sender 在 drop 之前放入的消息此时一定可见，再检查一次.
+        self.shared
+            .heap
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or(TryRecvError::Disconnected)
+    }
+
+    /// 阻塞直到有消息，取出当前最大的一条. 所有 Sender 都被丢弃并且堆已空时返回 Disconnected.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut registered = false;
+        let result = loop {
+            match self.try_recv() {
+                Ok(msg) => break Ok(msg),
+                Err(TryRecvError::Empty) => {}
+                Err(_) => break Err(RecvError::Disconnected),
+            }
+            // 先登记再检查一次，登记之前到达的消息不会错过唤醒.
+            if registered {
+                thread::park();
+            } else {
+                self.shared.waiter.register();
+                registered = true;
+            }
+        };
+        if registered {
+            self.shared.waiter.unregister();
+        }
+        result
+    }
+}
+
+impl<T> Receiver<T> {
+    /// 堆中的消息数，只是某一时刻的快照.
+    pub fn len(&self) -> usize {
+        self.shared.heap.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn largest_first() {
+        let (sender, mut receiver) = channel();
+        for i in [3, 1, 4, 1, 5] {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.len(), 5);
+        drop(sender);
+        let mut received = Vec::new();
+        while let Ok(msg) = receiver.recv() {
+            received.push(msg);
+        }
+        assert_eq!(received, [5, 4, 3, 1, 1]);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_with_priority() {
+        // 消息类型没有实现 Ord.
+        struct Task(&'static str);
+
+        let (sender, mut receiver) = channel();
+        sender.send_with_priority(Task("later"), 10).unwrap();
+        sender.send_with_priority(Task("urgent"), 0).unwrap();
+        sender.send_with_priority(Task("soon"), 5).unwrap();
+        let order: Vec<_> = (0..3)
+            .map(|_| receiver.try_recv().unwrap().into_inner().0)
+            .collect();
+        assert_eq!(order, ["urgent", "soon", "later"]);
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[test]
+    fn recv_blocks_until_send() {
+        let (sender, mut receiver) = channel();
+        let other = sender.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(5));
+            other.send(7).unwrap();
+        });
+        assert_eq!(receiver.recv(), Ok(7));
+        handle.join().unwrap();
+        drop(receiver);
+        assert_eq!(sender.send(1).unwrap_err().0, 1);
+    }
+}
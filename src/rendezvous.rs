@@ -0,0 +1,296 @@
+//! 零容量的 rendezvous channel: send 要等到接收方真正取走消息才返回，两端在这一点上严格握手.
+//! 与 One-Shot channel 不同，发送方会被阻塞，而且同一对 Sender/Receiver 可以反复交换消息:
+//!     1.send 把消息写入唯一的槽位并设置 ready，唤醒接收方，然后停放；
+//!     2.recv 用 compare_exchange 清除 ready 取得消息，再设置 taken 并唤醒发送方；
+//!     3.发送方醒来看到 taken 之后清除它，send 返回. 此时槽位重新空出，可以开始下一次交换.
+//! 接收方在消息被取走之前被丢弃时，发送方同样用 compare_exchange 清除 ready，把消息拿回来.
+//! 两边对 ready 的竞争只有一方会成功，所以消息既不会丢失也不会被读取两次.
+//! send_timeout 超时的处理与此相同: 发送方抢先清除 ready 就拿回消息，否则说明接收方恰好取走了它，
+//! 这一次交换已经完成，send_timeout 返回 Ok.
+
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::sync::{Arc, AtomicBool, UnsafeCell};
+use crate::waiter::Waiter;
+use crate::{RecvError, RecvTimeoutError, SendError, SendTimeoutError};
+
+/// 创建一个 rendezvous channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        message: UnsafeCell::new(MaybeUninit::uninit()),
+        ready: AtomicBool::new(false),
+        taken: AtomicBool::new(false),
+        disconnected: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        send_waiter: Waiter::new(),
+        recv_waiter: Waiter::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+struct Shared<T> {
+    message: UnsafeCell<MaybeUninit<T>>,
+    // ready : 槽位中有一条还没被取走的消息.
+    ready: AtomicBool,
+    // taken : 接收方已经取走了这一次 send 的消息，由发送方在返回之前清除.
+    taken: AtomicBool,
+    // disconnected : Sender 是否已经被丢弃.
+    disconnected: AtomicBool,
+    // closed : Receiver 是否已经被丢弃.
+    closed: AtomicBool,
+    send_waiter: Waiter,
+    recv_waiter: Waiter,
+}
+
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    /// 清除 ready 并取出消息. 发送方和接收方都可能调用，只有一方会成功.
+    fn claim(&self) -> Option<T> {
+        self.ready
+            .compare_exchange(true, false, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        Some(
+            self.message
+                .with(|ptr| unsafe { (*ptr).assume_init_read() }),
+        )
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        if self.ready.load(Ordering::Relaxed) {
+            self.message
+                .with_mut(|ptr| unsafe { (*ptr).assume_init_drop() })
+        }
+    }
+}
+
+/// 只有一个 Sender，send 需要 &mut self，所以槽位不会被并发写入.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 发送消息并阻塞，直到接收方通过 recv 取走它.
+    /// Receiver 在取走消息之前被丢弃(或者调用时已经被丢弃)时，通过 SendError 交还消息.
+    pub fn send(&mut self, msg: T) -> Result<(), SendError<T>> {
+        self.send_deadline(msg, None)
+            .map_err(|err| SendError(err.into_inner()))
+    }
+
+    /// 与 send 相同，但最多等待 timeout. 接收方在超时之前没有取走消息时返回 Timeout，
+    /// 消息被交还，槽位重新空出，之后可以重试.
+    pub fn send_timeout(&mut self, msg: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        // 超时太长以至于无法表示时当作永远等待.
+        self.send_deadline(msg, Instant::now().checked_add(timeout))
+    }
+
+    fn send_deadline(
+        &mut self,
+        msg: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        let shared = &*self.shared;
+        if shared.closed.load(Ordering::Acquire) {
+            return Err(SendTimeoutError::Disconnected(msg));
+        }
+        // 上一次 send 返回之前 ready 已经被清除，这里没有并发的读取者.
+        shared.message.with_mut(|ptr| unsafe { (*ptr).write(msg) });
+        shared.ready.store(true, Ordering::Release);
+        shared.recv_waiter.unpark();
+
+        let woken = shared.send_waiter.park_while_until(
+            || !shared.taken.load(Ordering::Acquire) && !shared.closed.load(Ordering::Acquire),
+            deadline,
+        );
+        if !shared.taken.load(Ordering::Acquire) {
+            // 超时或者 Receiver 已经被丢弃. 如果接收方没有抢先取走消息，就把消息拿回来.
+            if let Some(msg) = shared.claim() {
+                return Err(if woken {
+                    SendTimeoutError::Disconnected(msg)
+                } else {
+                    SendTimeoutError::Timeout(msg)
+                });
+            }
+            // 接收方已经取走了消息，taken 很快就会被设置.
+            shared
+                .send_waiter
+                .park_while(|| !shared.taken.load(Ordering::Acquire));
+        }
+        shared.taken.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.disconnected.store(true, Ordering::Release);
+        self.shared.recv_waiter.unpark();
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// 阻塞直到 Sender 发送一条消息，取走它并让那一次 send 返回.
+    /// Sender 被丢弃之后返回 Disconnected.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.recv_deadline(None)
+            .map_err(|_| RecvError::Disconnected)
+    }
+
+    /// 与 recv 相同，但最多等待 timeout. 超时之前 Sender 没有发送时返回 Timeout，
+    /// 之后的 send 仍然会等待下一次 recv.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Instant::now().checked_add(timeout))
+    }
+
+    fn recv_deadline(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let shared = &*self.shared;
+        loop {
+            let woken = shared.recv_waiter.park_while_until(
+                || {
+                    !shared.ready.load(Ordering::Acquire)
+                        && !shared.disconnected.load(Ordering::Acquire)
+                },
+                deadline,
+            );
+            // 超时的同时消息可能恰好到达，仍然取走它.
+            if let Some(msg) = shared.claim() {
+                shared.taken.store(true, Ordering::Release);
+                shared.send_waiter.unpark();
+                return Ok(msg);
+            }
+            if shared.disconnected.load(Ordering::Acquire) {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            if !woken {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            // 看到了 ready，但发送方的 send_timeout 恰好超时，抢先拿回了消息，继续等待.
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.send_waiter.unpark();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn send_waits_for_recv() {
+        let (mut sender, mut receiver) = channel();
+        let received = Arc::new(AtomicUsize::new(0));
+        let counter = received.clone();
+        let handle = thread::spawn(move || {
+            for i in 0..3 {
+                thread::sleep(Duration::from_millis(5));
+                assert_eq!(receiver.recv(), Ok(i));
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        for i in 0..3 {
+            sender.send(i).unwrap();
+            // send 返回时接收方一定已经取走了这条消息.
+            assert!(received.load(Ordering::SeqCst) >= i);
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn disconnect() {
+        let (sender, mut receiver) = channel::<i32>();
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+
+        let (mut sender, receiver) = channel();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            drop(receiver);
+        });
+        // Receiver 没有取走消息就被丢弃，消息被交还.
+        assert_eq!(sender.send(String::from("hello")).unwrap_err().0, "hello");
+        handle.join().unwrap();
+        assert!(sender.send(String::new()).is_err());
+    }
+
+    #[test]
+    fn ping_pong() {
+        const ROUNDS: u32 = 1000;
+        let (mut ping, mut ping_rx) = channel();
+        let (mut pong, mut pong_rx) = channel();
+        let handle = thread::spawn(move || {
+            while let Ok(n) = ping_rx.recv() {
+                pong.send(n + 1).unwrap();
+            }
+        });
+        let mut n = 0;
+        for _ in 0..ROUNDS {
+            ping.send(n).unwrap();
+            n = pong_rx.recv().unwrap();
+        }
+        assert_eq!(n, ROUNDS);
+        drop(ping);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn timeouts() {
+        let (mut sender, mut receiver) = channel();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        // 没有接收方来取，消息被交还，槽位重新空出.
+        let err = sender
+            .send_timeout(String::from("late"), Duration::from_millis(5))
+            .unwrap_err();
+        assert!(matches!(&err, SendTimeoutError::Timeout(msg) if msg == "late"));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        let handle = thread::spawn(move || {
+            let msg = receiver.recv_timeout(Duration::from_secs(10));
+            (receiver, msg)
+        });
+        sender
+            .send_timeout(err.into_inner(), Duration::from_secs(10))
+            .unwrap();
+        let (mut receiver, msg) = handle.join().unwrap();
+        assert_eq!(msg.as_deref(), Ok("late"));
+
+        drop(sender);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        let (mut sender, receiver) = channel();
+        drop(receiver);
+        assert!(matches!(
+            sender.send_timeout(1, Duration::from_secs(10)),
+            Err(SendTimeoutError::Disconnected(1))
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! select! 同时等待多个 One-Shot channel，哪个先就绪就执行哪个分支.
+//! 当前线程把自己登记在每个 Receiver 所在 Channel 的 waiter 中，然后 park；
+//! 任何一个 Sender 发送(或全部 Sender 断开)都会唤醒它. 醒来之后按分支的书写顺序检查，
+//! 只有确认就绪的那一个 Receiver 会被 try_recv，所以多个通道同时就绪时也只会取走一条消息，
+//! 其余的消息留在各自的通道中，下一次 select! 或 recv 仍然能收到.
+
+use crate::state;
+use crate::{Receiver, RecvError, TryRecvError};
+
+/// select! 展开后的代码需要访问 Receiver 的内部状态，这些方法只供宏使用.
+#[doc(hidden)]
+impl<T> Receiver<'_, T> {
+    /// 消息已经到达、Sender 调用了 cancel，或者所有 Sender 都已断开: 接下来的 try_recv 不会返回 Empty.
+    /// 已经取走过消息的 Receiver 永远不会再就绪.
+    pub fn __select_is_complete(&self) -> bool {
+        !self.received
+            && matches!(
+                self.inner.state(),
+                state::READY | state::CANCELLED | state::CLOSED_BY_SENDER
+            )
+    }
+
+    pub fn __select_register(&self) {
+        self.inner.waiter.register();
+    }
+
+    pub fn __select_unregister(&self) {
+        self.inner.waiter.unregister();
+    }
+
+    pub fn __select_take(&mut self) -> Result<T, crate::RecvError> {
+        match self.try_recv() {
+            Ok(msg) => Ok(msg),
+            Err(TryRecvError::Disconnected) => Err(crate::RecvError::Disconnected),
+            Err(TryRecvError::Cancelled) => Err(crate::RecvError::Cancelled),
+            Err(TryRecvError::Empty) => unreachable!("select! arm taken before it was ready"),
+        }
+    }
+}
+
+/// select2 的结果: 先就绪的是第一个 Receiver(Left)还是第二个(Right).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// 等待两个 One-Shot channel 中先到达的那条消息，另一个 Receiver 随之被丢弃
+/// (它的 Sender 之后的 send 会通过 SendError 拿回消息).
+/// 当前线程同时登记在两个 Channel 的 waiter 中，所以任何一个 Sender 都能唤醒它.
+/// 一个 Sender 断开或 cancel 时继续等待另一个；两个都不会再有消息时返回 Err:
+/// 两者都是 cancel 时为 Cancelled，否则为 Disconnected. 两条消息同时就绪时返回 Left.
+pub fn select2<A, B>(
+    mut a: Receiver<'_, A>,
+    mut b: Receiver<'_, B>,
+) -> Result<Either<A, B>, RecvError> {
+    fn poll<T>(rx: &mut Receiver<'_, T>, failed: &mut Option<RecvError>) -> Option<T> {
+        if failed.is_some() {
+            return None;
+        }
+        match rx.try_recv() {
+            Ok(msg) => return Some(msg),
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => *failed = Some(RecvError::Disconnected),
+            Err(TryRecvError::Cancelled) => *failed = Some(RecvError::Cancelled),
+        }
+        None
+    }
+
+    let (mut a_failed, mut b_failed) = (None, None);
+    let mut registered = false;
+    let result = loop {
+        if let Some(msg) = poll(&mut a, &mut a_failed) {
+            break Ok(Either::Left(msg));
+        }
+        if let Some(msg) = poll(&mut b, &mut b_failed) {
+            break Ok(Either::Right(msg));
+        }
+        match (a_failed, b_failed) {
+            (Some(RecvError::Cancelled), Some(RecvError::Cancelled)) => {
+                break Err(RecvError::Cancelled)
+            }
+            (Some(_), Some(_)) => break Err(RecvError::Disconnected),
+            _ => {}
+        }
+        if registered {
+            std::thread::park();
+        } else {
+            a.__select_register();
+            b.__select_register();
+            registered = true;
+        }
+    };
+    a.__select_unregister();
+    b.__select_unregister();
+    result
+}
+
+/// 阻塞直到其中一个 Receiver 就绪，然后执行对应的分支:
+///
+/// ```
+/// use my_channel::{channel, select};
+///
+/// let (_s1, mut r1) = channel::<i32>();
+/// let (s2, mut r2) = channel::<&str>();
+/// s2.send("hello").unwrap();
+/// let n = select!(
+///     r1 => |msg| msg.unwrap() as usize,
+///     r2 => |msg| msg.unwrap().len(),
+/// );
+/// assert_eq!(n, 5);
+/// ```
+///
+/// 每个分支写作 `receiver => |msg| 表达式`，msg 的类型是 `Result<T, RecvError>`:
+/// 所有 Sender 都没有发送就被丢弃、或者 Sender 调用了 cancel 时，该分支同样会被选中，
+/// 分别得到 Disconnected 和 Cancelled.
+/// 分支的表达式直接展开在 select! 所在的位置(不是闭包)，其中的 return / break / ? 作用于外层，
+/// 所有分支的类型必须相同. 多个通道同时就绪时选择写在前面的那一个.
+///
+/// receiver 需要是一个 Receiver 变量(或 &mut Receiver)，宏会多次对它求值.
+/// 已经取走过消息的 Receiver 不会再被选中；如果所有 Receiver 都是这样，select! 将永远阻塞.
+#[macro_export]
+macro_rules! select {
+    ($($rx:expr => |$msg:pat_param| $body:expr),+ $(,)?) => {{
+        let fired = {
+            let mut registered = false;
+            loop {
+                if let Some(fired) = $crate::select!(@poll 0usize; $($rx,)+) {
+                    break fired;
+                }
+                // 先登记再检查一次，登记之前到达的消息不会错过唤醒.
+                if registered {
+                    ::std::thread::park();
+                } else {
+                    $($rx.__select_register();)+
+                    registered = true;
+                }
+            }
+        };
+        $($rx.__select_unregister();)+
+        $crate::select!(@take fired; $($rx => |$msg| $body,)+)
+    }};
+    (@poll $index:expr;) => {
+        None
+    };
+    (@poll $index:expr; $rx:expr, $($rest:expr,)*) => {
+        if $rx.__select_is_complete() {
+            Some($index)
+        } else {
+            $crate::select!(@poll $index + 1; $($rest,)*)
+        }
+    };
+    (@take $index:expr;) => {
+        unreachable!()
+    };
+    (@take $index:expr; $rx:expr => |$msg:pat_param| $body:expr, $($rest:tt)*) => {
+        if $index == 0 {
+            let $msg = $rx.__select_take();
+            $body
+        } else {
+            $crate::select!(@take $index - 1; $($rest)*)
+        }
+    };
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{channel, RecvError};
+
+    #[test]
+    fn first_ready_wins() {
+        let (_s1, mut r1) = channel::<i32>();
+        let (s2, mut r2) = channel::<String>();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            s2.send(String::from("hello rustacean!")).unwrap();
+        });
+        let msg = select!(
+            r1 => |msg| format!("r1: {msg:?}"),
+            r2 => |msg| msg.unwrap(),
+        );
+        assert_eq!(msg, "hello rustacean!");
+        assert!(!r1.is_ready());
+    }
+
+    #[test]
+    fn all_ready_picks_first_and_keeps_the_rest() {
+        let (s1, mut r1) = channel();
+        let (s2, mut r2) = channel();
+        s1.send(1).unwrap();
+        s2.send(2).unwrap();
+        let first = select!(r1 => |msg| msg, r2 => |msg| msg);
+        assert_eq!(first, Ok(1));
+        // r1 已经取走了消息，不会再被选中; r2 的消息仍然留在通道中.
+        let second = select!(r1 => |msg| msg, r2 => |msg| msg);
+        assert_eq!(second, Ok(2));
+    }
+
+    #[test]
+    fn disconnected_arm_fires() {
+        let (_s1, mut r1) = channel::<i32>();
+        let (s2, mut r2) = channel::<i32>();
+        thread::spawn(move || drop(s2));
+        let fired = select!(r1 => |_| 1, r2 => |msg| {
+            assert_eq!(msg, Err(RecvError::Disconnected));
+            2
+        });
+        assert_eq!(fired, 2);
+    }
+
+    #[test]
+    fn select2_staggered() {
+        use crate::select2;
+        use crate::Either;
+
+        let (fast_tx, fast) = channel();
+        let (slow_tx, slow) = channel();
+        let selecting = thread::spawn(move || select2(slow, fast));
+        fast_tx.send("primary").unwrap();
+        assert_eq!(selecting.join().unwrap(), Ok(Either::Right("primary")));
+        // select2 已经返回，另一个 Receiver 随之被丢弃.
+        slow_tx.send(2).unwrap_err();
+
+        // 一个断开之后继续等待另一个.
+        let (a_tx, a) = channel::<i32>();
+        let (b_tx, b) = channel::<i32>();
+        drop(a_tx);
+        let selecting = thread::spawn(move || select2(a, b));
+        b_tx.send(7).unwrap();
+        assert_eq!(selecting.join().unwrap(), Ok(Either::Right(7)));
+
+        let (a_tx, a) = channel::<i32>();
+        let (b_tx, b) = channel::<i32>();
+        a_tx.cancel();
+        drop(b_tx);
+        assert_eq!(select2(a, b), Err(RecvError::Disconnected));
+    }
+}
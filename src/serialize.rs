@@ -0,0 +1,63 @@
+//! 把一次接收的结果直接序列化，例如把另一个线程算出的结果写进网络响应.
+//! 通道本身无法被序列化，这里只序列化收到的消息. Sender 没有发送就被丢弃，或者调用了 cancel 时，
+//! RecvError 通过 serde::ser::Error::custom 转换成 Serializer 自己的错误类型，
+//! 调用方只需要处理一种错误.
+
+use serde::ser::Error as _;
+use serde::{Serialize, Serializer};
+
+use crate::Receiver;
+
+impl<T: Serialize> Receiver<'_, T> {
+    /// 与 recv 相同地阻塞等待消息，然后用 ser 序列化它.
+    ///
+    /// # Panics
+    /// 与 recv 相同，消息已经通过 try_recv 取走后调用会 panic.
+    pub fn recv_and_serialize<S: Serializer>(self, ser: S) -> Result<S::Ok, S::Error> {
+        match self.recv() {
+            Ok(msg) => msg.serialize(ser),
+            Err(err) => Err(S::Error::custom(err)),
+        }
+    }
+
+    /// 阻塞等待消息并序列化成 JSON 字符串. 接收失败时返回的 serde_json::Error 的消息
+    /// 就是 RecvError 的 Display.
+    #[cfg(feature = "serde_json")]
+    pub fn recv_json(self) -> Result<String, serde_json::Error> {
+        let mut buf = Vec::new();
+        self.recv_and_serialize(&mut serde_json::Serializer::new(&mut buf))?;
+        // serde_json 只会写出合法的 UTF-8.
+        Ok(String::from_utf8(buf).expect("serde_json produced invalid UTF-8"))
+    }
+}
+
+#[cfg(all(test, feature = "serde_json", not(loom)))]
+mod test {
+    use std::collections::BTreeMap;
+    use std::thread;
+
+    use crate::channel;
+
+    #[test]
+    fn recv_json() {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || sender.send(BTreeMap::from([("answer", 42)])).unwrap());
+        assert_eq!(receiver.recv_json().unwrap(), r#"{"answer":42}"#);
+        handle.join().unwrap();
+
+        let (sender, receiver) = channel::<i32>();
+        drop(sender);
+        let err = receiver.recv_json().unwrap_err();
+        assert_eq!(err.to_string(), crate::RecvError::Disconnected.to_string());
+    }
+
+    #[test]
+    fn recv_and_serialize_into_value() {
+        let (sender, receiver) = channel();
+        sender.send(vec![1, 2, 3]).unwrap();
+        let value = receiver
+            .recv_and_serialize(serde_json::value::Serializer)
+            .unwrap();
+        assert_eq!(value, serde_json::json!([1, 2, 3]));
+    }
+}
@@ -0,0 +1,373 @@
+//! 容量在编译期确定、不分配内存的 SPSC(单生产者/单消费者) 环形缓冲区，例如音频管线中
+//! 回调线程与处理线程之间的样本队列. 与 bounded 模块的区别在于:
+//!     1.槽位是 Channel 内联的 [MaybeUninit<T>; N]，Channel::new 是 const fn，
+//!       可以放在栈上或 static 中，不需要 alloc；
+//!     2.push / pop 从不阻塞也不加锁，队列满/空时立即返回，在关闭 std feature 时同样可用；
+//!       std 下另外提供与 One-Shot channel 相同 park/unpark 方式的阻塞 send / recv.
+//! head/tail 只在 [0, 2N) 内循环: tail - head(模 2N)就是消息数，满(N)与空(0)不会混淆，
+//! 并且下标永远不会在 usize 上回绕 —— 在 32 位目标上长时间运行的管线也不会出错.
+//! 生产者只写 tail，消费者只写 head: 写入槽位之后用 Release 推进自己的下标，
+//! 对方用 Acquire 读取，读到新下标时也能看到槽位中的内容，或者确认槽位已经读完可以覆盖.
+
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering;
+
+use crate::cache_padded::CachePadded;
+use crate::sync::{const_fn, AtomicBool, AtomicUsize, UnsafeCell};
+#[cfg(feature = "std")]
+use crate::{waiter::Waiter, RecvError, SendError};
+
+pub struct Channel<T, const N: usize> {
+    buffer: [UnsafeCell<MaybeUninit<T>>; N],
+    // head : 下一个要读取的位置，只由 Receiver 推进.
+    head: CachePadded<AtomicUsize>,
+    // tail : 下一个要写入的位置，只由 Sender 推进.
+    tail: CachePadded<AtomicUsize>,
+    // disconnected / closed : 当前这一对的 Sender / Receiver 是否已经被丢弃，split 时重置.
+    disconnected: AtomicBool,
+    closed: AtomicBool,
+    #[cfg(feature = "std")]
+    send_waiter: Waiter,
+    #[cfg(feature = "std")]
+    recv_waiter: Waiter,
+}
+
+impl<T, const N: usize> Channel<T, N> {
+    const_fn! {
+        /// N 为 0 时编译失败.
+        pub const fn new() -> Self {
+            const { assert!(N > 0, "capacity must be non-zero") };
+            Self {
+                buffer: slots(),
+                head: CachePadded::new(AtomicUsize::new(0)),
+                tail: CachePadded::new(AtomicUsize::new(0)),
+                disconnected: AtomicBool::new(false),
+                closed: AtomicBool::new(false),
+                #[cfg(feature = "std")]
+                send_waiter: Waiter::new(),
+                #[cfg(feature = "std")]
+                recv_waiter: Waiter::new(),
+            }
+        }
+    }
+
+    /// 与 One-Shot channel 的 split 一样，通过独占借用保证只有一个生产者和一个消费者.
+    /// 与 bounded 相同，不会清空队列: 上一对 Sender/Receiver 留下的消息仍会被新的 Receiver 收到.
+    pub fn split(&mut self) -> (Sender<'_, T, N>, Receiver<'_, T, N>) {
+        self.disconnected = AtomicBool::new(false);
+        self.closed = AtomicBool::new(false);
+        (Sender { channel: self }, Receiver { channel: self })
+    }
+
+    /// 槽位数，即 N.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// 队列中的消息数. 与 BoundedChannel::len 相同，只是一个可能过时的快照.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        distance(head, tail, N)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slot(&self, index: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.buffer[if index >= N { index - N } else { index }]
+    }
+}
+
+#[cfg(not(loom))]
+const fn slots<T, const N: usize>() -> [UnsafeCell<MaybeUninit<T>>; N] {
+    [const { UnsafeCell::new(MaybeUninit::uninit()) }; N]
+}
+
+// loom 的 UnsafeCell::new 不是 const fn.
+#[cfg(loom)]
+fn slots<T, const N: usize>() -> [UnsafeCell<MaybeUninit<T>>; N] {
+    core::array::from_fn(|_| UnsafeCell::new(MaybeUninit::uninit()))
+}
+
+/// [0, 2N) 内从 head 到 tail 的距离.
+fn distance(head: usize, tail: usize, n: usize) -> usize {
+    if tail >= head {
+        tail - head
+    } else {
+        tail + 2 * n - head
+    }
+}
+
+/// 在 [0, 2N) 内前进一步.
+fn advance(index: usize, n: usize) -> usize {
+    if index + 1 == 2 * n {
+        0
+    } else {
+        index + 1
+    }
+}
+
+impl<T, const N: usize> Default for Channel<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Channel<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Channel")
+            .field("len", &self.len())
+            .field("capacity", &N)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl<T, const N: usize> Sync for Channel<T, N> where T: Send {}
+
+impl<T, const N: usize> Drop for Channel<T, N> {
+    /// 释放 head 与 tail 之间还没有被取走的消息.
+    fn drop(&mut self) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let mut index = self.head.load(Ordering::Relaxed);
+        while index != tail {
+            self.slot(index)
+                .with_mut(|ptr| unsafe { (*ptr).assume_init_drop() });
+            index = advance(index, N);
+        }
+    }
+}
+
+/// push 在队列已满时返回的错误，消息原样交还.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Full<T>(pub T);
+
+impl<T> Full<T> {
+    /// 取回没有放入队列的消息.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Full").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a full channel".fmt(f)
+    }
+}
+
+impl<T> core::error::Error for Full<T> {}
+
+pub struct Sender<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<T, const N: usize> Sender<'_, T, N> {
+    /// 把消息放入队列，不会阻塞. 队列已满时通过 Full 交还消息.
+    pub fn push(&mut self, msg: T) -> Result<(), Full<T>> {
+        let channel = self.channel;
+        // tail 只有 Sender 自己会修改，Relaxed 即可；Acquire 读取 head，确认槽位已经被读完.
+        let tail = channel.tail.load(Ordering::Relaxed);
+        if distance(channel.head.load(Ordering::Acquire), tail, N) == N {
+            return Err(Full(msg));
+        }
+        channel
+            .slot(tail)
+            .with_mut(|ptr| unsafe { (*ptr).write(msg) });
+        channel.tail.store(advance(tail, N), Ordering::Release);
+        #[cfg(feature = "std")]
+        channel.recv_waiter.unpark();
+        Ok(())
+    }
+
+    /// 发送一条消息，队列已满时阻塞，直到消费者取走一条消息.
+    /// Receiver 已经被丢弃时通过 SendError 交还消息，而不是永远停放.
+    #[cfg(feature = "std")]
+    pub fn send(&mut self, mut msg: T) -> Result<(), SendError<T>> {
+        let channel = self.channel;
+        loop {
+            if channel.closed.load(Ordering::Acquire) {
+                return Err(SendError(msg));
+            }
+            match self.push(msg) {
+                Ok(()) => return Ok(()),
+                Err(Full(rejected)) => msg = rejected,
+            }
+            channel.send_waiter.park_while(|| {
+                !channel.closed.load(Ordering::Acquire)
+                    && distance(
+                        channel.head.load(Ordering::Acquire),
+                        channel.tail.load(Ordering::Relaxed),
+                        N,
+                    ) == N
+            });
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<'_, T, N> {
+    /// 唤醒等待中的 Receiver，让它读完剩下的消息之后返回 Disconnected.
+    fn drop(&mut self) {
+        // Release: 看到 disconnected 的 Receiver 也能看到之前 push 的所有消息.
+        self.channel.disconnected.store(true, Ordering::Release);
+        #[cfg(feature = "std")]
+        self.channel.recv_waiter.unpark();
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Sender<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("len", &self.channel.len())
+            .finish()
+    }
+}
+
+pub struct Receiver<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<T, const N: usize> Receiver<'_, T, N> {
+    /// 取出最早的一条消息，不会阻塞. 队列为空时返回 None.
+    pub fn pop(&mut self) -> Option<T> {
+        let channel = self.channel;
+        // head 只有 Receiver 自己会修改，Relaxed 即可；Acquire 读取 tail，确认槽位已经写完.
+        let head = channel.head.load(Ordering::Relaxed);
+        if channel.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+        let msg = channel
+            .slot(head)
+            .with(|ptr| unsafe { (*ptr).assume_init_read() });
+        channel.head.store(advance(head, N), Ordering::Release);
+        #[cfg(feature = "std")]
+        channel.send_waiter.unpark();
+        Some(msg)
+    }
+
+    /// 接收一条消息，队列为空时阻塞，直到生产者发送一条消息.
+    /// Sender 已经被丢弃并且队列为空时返回 Disconnected.
+    #[cfg(feature = "std")]
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let channel = self.channel;
+        loop {
+            if let Some(msg) = self.pop() {
+                return Ok(msg);
+            }
+            if channel.disconnected.load(Ordering::Acquire) {
+                // 看到 disconnected 之后，Sender 发送过的消息都已可见，再取一次.
+                return self.pop().ok_or(RecvError::Disconnected);
+            }
+            channel.recv_waiter.park_while(|| {
+                channel.tail.load(Ordering::Acquire) == channel.head.load(Ordering::Relaxed)
+                    && !channel.disconnected.load(Ordering::Acquire)
+            });
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<'_, T, N> {
+    /// 让阻塞在满队列上的 send 返回 SendError，而不是永远等下去.
+    fn drop(&mut self) {
+        self.channel.closed.store(true, Ordering::Release);
+        #[cfg(feature = "std")]
+        self.channel.send_waiter.unpark();
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Receiver<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("len", &self.channel.len())
+            .finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::rc::Rc;
+    use std::thread;
+
+    use super::*;
+
+    // new 是 const fn，可以直接放在 static 中.
+    static _STATIC: Channel<u8, 8> = Channel::new();
+
+    #[test]
+    fn wraparound() {
+        let mut channel = Channel::<u32, 4>::new();
+        let (mut sender, mut receiver) = channel.split();
+        let mut next = 0;
+        let mut expected = 0;
+        // 每一轮放入 3 条、取出 3 条，下标多次越过 N 和 2N.
+        for _ in 0..10 {
+            for _ in 0..3 {
+                sender.push(next).unwrap();
+                next += 1;
+            }
+            for _ in 0..3 {
+                assert_eq!(receiver.pop(), Some(expected));
+                expected += 1;
+            }
+        }
+        for i in 0..4 {
+            sender.push(i).unwrap();
+        }
+        assert_eq!(sender.push(4), Err(Full(4)));
+        assert_eq!(Full(4).to_string(), "sending on a full channel");
+        assert_eq!(receiver.channel.len(), 4);
+        assert_eq!(
+            (0..5).map(|_| receiver.pop()).collect::<Vec<_>>(),
+            [Some(0), Some(1), Some(2), Some(3), None]
+        );
+    }
+
+    #[test]
+    fn drop_in_flight() {
+        let value = Rc::new(());
+        let mut channel = Channel::<Rc<()>, 3>::new();
+        {
+            let (mut sender, mut receiver) = channel.split();
+            for _ in 0..3 {
+                sender.push(value.clone()).unwrap();
+            }
+            drop(receiver.pop());
+            sender.push(value.clone()).unwrap();
+        }
+        assert_eq!(Rc::strong_count(&value), 4);
+        drop(channel);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn blocking_send_and_recv() {
+        let mut channel = Channel::<usize, 2>::new();
+        let (mut sender, mut receiver) = channel.split();
+        thread::scope(|s| {
+            s.spawn(move || {
+                for i in 0..1000 {
+                    sender.send(i).unwrap();
+                }
+            });
+            for i in 0..1000 {
+                assert_eq!(receiver.recv(), Ok(i));
+            }
+            assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        });
+        drop(receiver);
+
+        let (mut sender, receiver) = channel.split();
+        sender.push(1).unwrap();
+        sender.push(2).unwrap();
+        drop(receiver);
+        assert_eq!(sender.send(3), Err(SendError(3)));
+    }
+}
@@ -0,0 +1,52 @@
+//! One-Shot channel 的状态机. Channel 只用一个 AtomicU8 记录状态，每条路径都是下面的一条边，
+//! 由一次 compare_exchange(或只有当前持有者才会执行的 store)完成:
+//!
+//! ```text
+//!                 send 赢得写入权                 写入完成                try_recv
+//!     EMPTY ---------------------> WRITING ------------------> READY ------------> READING
+//!       |  ^                          |          (Release)       |     (Acquire)      |
+//!       |  +--------------------------+     <-- send_replace --+                    | 读取完成
+//!       |        send_with 的 f panic                            | take / Channel 的 Drop
+//!       |                                                         v                    v
+//!       |                                                   (释放或取回消息)       RECEIVED
+//!       +----- cancel ------------------------------> CANCELLED
+//!       +----- 最后一个 Sender 被丢弃 ----------------> CLOSED_BY_SENDER
+//!       +----- Receiver 被丢弃 -----------------------> CLOSED_BY_RECEIVER
+//! ```
+//!
+//! send、cancel、Sender 的 drop 与 Receiver 的 drop 之间的竞争都归结为对 EMPTY 的 compare_exchange，
+//! send_replace 覆盖旧消息(READY -> WRITING)与 try_recv(READY -> READING)之间的竞争归结为对 READY 的
+//! compare_exchange，每次都只有一方会成功，不再需要两两推敲多个标志之间的内存序:
+//!     1.send / cancel 失败说明消息已经发送、已经 cancel，或 Receiver 已经被丢弃，消息交还给调用者；
+//!     2.最后一个 Sender 被丢弃时若状态已不是 EMPTY，说明已经有 Sender 发送或 cancel，什么也不做；
+//!     3.Receiver 被丢弃时若消息正在写入或已经就绪，状态保持不变，消息留在通道中，
+//!       由 Channel::take 取回或由 Channel 的 Drop 释放.
+//! WRITING 期间 send_with 的 f panic 时回到 EMPTY(消息从未写入)，其他 Sender 仍然可以发送；
+//! 若这是最后一个 Sender，它随后的 drop 会进入 CLOSED_BY_SENDER，通道不会假装有一条就绪的消息.
+//! READING 只在 try_recv 读取 MaybeUninit 的期间短暂存在.
+//! peek / recv_ref 借出过引用之后(Channel 的 lent 标志)，send_replace 赢得 READY -> WRITING 后会直接回到 READY，
+//! 不读取也不覆盖旧消息，被引用的消息不会被移走.
+
+pub(crate) const EMPTY: u8 = 0;
+pub(crate) const WRITING: u8 = 1;
+pub(crate) const READY: u8 = 2;
+pub(crate) const READING: u8 = 3;
+pub(crate) const RECEIVED: u8 = 4;
+pub(crate) const CANCELLED: u8 = 5;
+pub(crate) const CLOSED_BY_SENDER: u8 = 6;
+pub(crate) const CLOSED_BY_RECEIVER: u8 = 7;
+
+/// Debug 输出中的状态名.
+pub(crate) fn name(state: u8) -> &'static str {
+    match state {
+        EMPTY => "Empty",
+        WRITING => "Writing",
+        READY => "Ready",
+        READING => "Reading",
+        RECEIVED => "Received",
+        CANCELLED => "Cancelled",
+        CLOSED_BY_SENDER => "ClosedBySender",
+        CLOSED_BY_RECEIVER => "ClosedByReceiver",
+        _ => unreachable!("invalid channel state {state}"),
+    }
+}
@@ -0,0 +1,61 @@
+//! 通道内部使用的并发原语. 平时就是 core/alloc/std 中的类型；
+//! 以 RUSTFLAGS="--cfg loom" 编译时换成 loom 的版本，由 loom 穷举线程交错和弱内存序下的行为.
+//! loom 的 UnsafeCell 只能通过 with / with_mut 访问，所以这里给 core 的 UnsafeCell
+//! 包了一层相同的接口，通道的代码只写一遍.
+
+#[cfg(not(loom))]
+mod imp {
+    pub(crate) use alloc::sync::Arc;
+    pub(crate) use core::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU8, AtomicUsize};
+    #[cfg(feature = "std")]
+    pub(crate) use std::sync::Mutex;
+    #[cfg(feature = "std")]
+    pub(crate) use std::thread;
+
+    pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            Self(core::cell::UnsafeCell::new(value))
+        }
+
+        pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+    }
+}
+
+#[cfg(loom)]
+mod imp {
+    pub(crate) use loom::cell::UnsafeCell;
+    pub(crate) use loom::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU8, AtomicUsize};
+    pub(crate) use loom::sync::{Arc, Mutex};
+
+    pub(crate) mod thread {
+        pub(crate) use loom::thread::*;
+
+        /// loom 没有 park_timeout. 超时等待在 loom 下退化为让出一次执行权，
+        /// 对调用方来说这只是一次虚假唤醒，等待循环本来就要处理.
+        pub(crate) fn park_timeout(_dur: std::time::Duration) {
+            yield_now();
+        }
+    }
+}
+
+pub(crate) use imp::*;
+
+/// loom 的原子类型和 Mutex 没有 const fn new，所以在 loom 下去掉构造函数上的 const.
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis const fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])* $vis const fn $($rest)*
+        #[cfg(loom)]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+pub(crate) use const_fn;
@@ -0,0 +1,100 @@
+//! 阻塞接收时的等待策略. 消息还没到达时，recv / recv_timeout / recv_deadline / recv_ref / wait_ready
+//! 先按策略自旋若干次，每次自旋之后重新检查状态，策略放弃自旋之后才登记线程并 park:
+//!     1.Park: 立即停放，与引入策略之前的行为相同. 适合等待时间较长的情况；
+//!     2.Spin: 从不停放，一直自旋到消息到达(或超时). 省去 park/unpark 的系统调用，
+//!       在发送方马上就会 send 的紧密交接中延迟最低，但等待期间一直占用一个核；
+//!     3.SpinThenPark: 先自旋 spins 次，消息仍未到达时再停放，兼顾两者.
+//!       默认策略是 SpinThenPark::DEFAULT(自旋 100 次): 微秒级的往返在自旋期间就能完成，
+//!       省去一次上下文切换；等待更久时只多付出不到一微秒的自旋，随后照常停放.
+//!       只有一个核时发送方无法在自旋期间运行，自旋只会推迟它，所以 SpinThenPark 此时直接停放.
+//! 策略只影响阻塞等待，.await 一个 Receiver 时总是登记 Waker，不会自旋.
+//! 策略由 Channel::with_wait_strategy 或 Channel::split_with 指定，保存在 Channel 中，
+//! 所以必须是 'static 的: Park、Spin 以及常量的 SpinThenPark 都可以直接取引用.
+
+use core::hint;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// 自定义等待策略. 同一个通道可能在不同线程上被等待，所以要求 Sync.
+pub trait WaitStrategy: Sync {
+    /// 第 attempt 次(从 0 开始)检查发现消息还没到达时调用.
+    /// 返回 true 表示已经等待了一小段时间(例如 spin_loop 或 yield_now)，应当重新检查；
+    /// 返回 false 表示放弃自旋，随后登记当前线程并停放. 返回 false 之后不会再被调用.
+    fn spin(&self, attempt: u32) -> bool;
+}
+
+/// 立即停放.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Park;
+
+impl WaitStrategy for Park {
+    fn spin(&self, _attempt: u32) -> bool {
+        false
+    }
+}
+
+/// 一直自旋，从不停放. 在单核机器上，发送方要等自旋的线程用完时间片才能运行，反而更慢.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Spin;
+
+impl WaitStrategy for Spin {
+    fn spin(&self, _attempt: u32) -> bool {
+        hint::spin_loop();
+        true
+    }
+}
+
+/// 先自旋 spins 次，消息仍未到达时停放. 只有一个核时不自旋.
+#[derive(Debug, Clone, Copy)]
+pub struct SpinThenPark {
+    pub spins: u32,
+}
+
+impl SpinThenPark {
+    /// Channel::new 使用的默认策略.
+    pub const DEFAULT: SpinThenPark = SpinThenPark { spins: 100 };
+}
+
+impl Default for SpinThenPark {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl WaitStrategy for SpinThenPark {
+    fn spin(&self, attempt: u32) -> bool {
+        if attempt < self.spins && multicore() {
+            hint::spin_loop();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 是否有多于一个核可用. 只查询一次，结果缓存在静态变量中: 0 未知，1 单核，2 多核.
+/// 这只是一个缓存而不是同步，用 core 的原子类型即可，loom 下也不需要穷举.
+fn multicore() -> bool {
+    static CORES: AtomicU8 = AtomicU8::new(0);
+    match CORES.load(Ordering::Relaxed) {
+        0 => {
+            let multi = std::thread::available_parallelism().is_ok_and(|n| n.get() > 1);
+            CORES.store(if multi { 2 } else { 1 }, Ordering::Relaxed);
+            multi
+        }
+        cores => cores == 2,
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spin_then_park_gives_up() {
+        let strategy = SpinThenPark { spins: 3 };
+        let spun = (0..10).take_while(|&i| strategy.spin(i)).count();
+        assert_eq!(spun, if multicore() { 3 } else { 0 });
+        assert!(!Park.spin(0));
+        assert!(Spin.spin(u32::MAX));
+    }
+}
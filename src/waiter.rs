@@ -0,0 +1,160 @@
+//! 等待方在停放之前把自己的线程句柄登记在 Waiter 中，通知方改变状态之后据此唤醒它.
+//! 线程句柄不再在 split 时固定下来，因此 Receiver 可以在线程之间移动.
+//! 异步的等待方登记的是当前任务的 Waker，通知方用同一条路径唤醒它.
+//! Receiver::on_ready 登记的是一个回调标记: 回调本身的类型与消息有关，保存在 Channel 中，
+//! unpark 只告诉通知方需要由它来调用回调.
+
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::task::Waker;
+use std::time::Instant;
+
+use crate::sync::thread::{self, Thread};
+use crate::sync::{const_fn, fence, AtomicBool, Mutex};
+
+/// 登记在 Waiter 中的等待方: 被停放的线程，或者返回了 Poll::Pending 的异步任务.
+enum Waiting {
+    Thread(Thread),
+    Task(Waker),
+    Callback,
+}
+
+pub(crate) struct Waiter {
+    // parked : 是否有等待方登记并准备停放. 通知方据此跳过没有必要的加锁和唤醒.
+    parked: AtomicBool,
+    waiting: Mutex<Option<Waiting>>,
+}
+
+impl Waiter {
+    const_fn! {
+        pub(crate) const fn new() -> Self {
+            Self {
+                parked: AtomicBool::new(false),
+                waiting: Mutex::new(None),
+            }
+        }
+    }
+
+    /// 登记当前线程. 调用之后必须再检查一次等待的条件，条件仍不满足时才能 park.
+    pub(crate) fn register(&self) {
+        *self.waiting.lock().unwrap() = Some(Waiting::Thread(thread::current()));
+        self.publish();
+    }
+
+    /// 登记当前任务的 Waker. 执行器可能在两次 poll 之间移动任务，所以每次 poll 都要调用，
+    /// will_wake 为 true 时保留原来的 Waker，避免没有必要的 clone.
+    /// 与 register 一样，调用之后必须再检查一次条件，条件仍不满足时才能返回 Poll::Pending.
+    pub(crate) fn register_waker(&self, waker: &Waker) {
+        let mut waiting = self.waiting.lock().unwrap();
+        match &*waiting {
+            Some(Waiting::Task(registered)) if registered.will_wake(waker) => {}
+            _ => *waiting = Some(Waiting::Task(waker.clone())),
+        }
+        drop(waiting);
+        self.publish();
+    }
+
+    /// 登记一个保存在别处的回调. 与 register 一样，调用之后必须再检查一次条件.
+    /// 回调只会被登记一次，之后的 unpark 都返回 true，由调用方决定回调是否已经被取走.
+    pub(crate) fn register_callback(&self) {
+        *self.waiting.lock().unwrap() = Some(Waiting::Callback);
+        self.publish();
+    }
+
+    fn publish(&self) {
+        self.parked.store(true, Ordering::Relaxed);
+        // 与 unpark 中的 fence 配对: 要么等待方在登记之后的检查中看到了通知方的修改，
+        // 要么通知方看到了 parked == true 并唤醒等待方，不会两者都错过.
+        fence(Ordering::SeqCst);
+    }
+
+    /// 等待结束后撤销登记，之后的通知不会再唤醒这个等待方.
+    pub(crate) fn unregister(&self) {
+        self.parked.store(false, Ordering::Relaxed);
+    }
+
+    /// 在 cond() 为 true 期间停放当前线程.
+    pub(crate) fn park_while(&self, cond: impl Fn() -> bool) {
+        self.register();
+        while cond() {
+            thread::park();
+        }
+        self.unregister();
+    }
+
+    /// 与 park_while 相同，但最多停放到 deadline(None 表示不限时)，用于各个 _timeout 方法.
+    /// cond() 变为 false 时返回 true，到达 deadline 时 cond() 仍为 true 则返回 false.
+    /// park_timeout 可能被虚假唤醒，每次醒来都重新计算剩余时间.
+    pub(crate) fn park_while_until(
+        &self,
+        cond: impl Fn() -> bool,
+        deadline: Option<Instant>,
+    ) -> bool {
+        self.register();
+        let done = loop {
+            if !cond() {
+                break true;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break false;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        };
+        self.unregister();
+        done
+    }
+
+    /// 通知方在修改状态之后调用，唤醒已经登记的等待方(如果有的话).
+    /// 先在锁内取出一份线程句柄或 Waker，释放锁之后再唤醒: 用户提供的 Waker 在 wake 中 panic 时
+    /// 不会毒化 Mutex，Waiter 之后仍然可以登记和唤醒.
+    /// 登记的是回调时返回 true，调用方应当在这之后调用它.
+    pub(crate) fn unpark(&self) -> bool {
+        fence(Ordering::SeqCst);
+        // 没有等待方时不去碰锁，只付出一次 fence 的代价.
+        if !self.parked.load(Ordering::Relaxed) {
+            return false;
+        }
+        let waiting = match &*self.waiting.lock().unwrap() {
+            Some(Waiting::Thread(thread)) => Some(Waiting::Thread(thread.clone())),
+            Some(Waiting::Task(waker)) => Some(Waiting::Task(waker.clone())),
+            Some(Waiting::Callback) => return true,
+            None => None,
+        };
+        match waiting {
+            Some(Waiting::Thread(thread)) => thread.unpark(),
+            Some(Waiting::Task(waker)) => waker.wake(),
+            Some(Waiting::Callback) | None => {}
+        }
+        false
+    }
+}
+
+/// 只显示登记的是哪个线程(或是一个异步任务)，不打印 Waker 本身.
+impl fmt::Debug for Waiting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Waiting::Thread(thread) => f.debug_tuple("Thread").field(&thread.id()).finish(),
+            Waiting::Task(_) => f.write_str("Task"),
+            Waiting::Callback => f.write_str("Callback"),
+        }
+    }
+}
+
+/// 用 try_lock 读取登记信息，即使在持有锁的路径上打印也不会死锁.
+impl fmt::Debug for Waiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Waiter");
+        d.field("parked", &self.parked.load(Ordering::Relaxed));
+        match self.waiting.try_lock() {
+            Ok(waiting) => d.field("waiting", &*waiting),
+            Err(_) => d.field("waiting", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
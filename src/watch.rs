@@ -0,0 +1,208 @@
+//! watch channel: 只保留最近一次发送的值，任意多个 Receiver 可以随时读取它.
+//! 与 One-Shot channel 不同，Sender 可以反复 send，每次都覆盖旧值:
+//!     1.值放在 Arc 中的 RwLock<T> 里，send 持有写锁替换它，Receiver 持有读锁 clone 它；
+//!     2.version 是一个 AtomicU64，每次 send 加一. 每个 Receiver 记住自己看到的版本，
+//!       recv 阻塞到 version 超过这个版本为止. 64 位计数不会回绕，不存在 ABA 问题；
+//!     3.等待的 Receiver 数量不定，用 Condvar 的 notify_all 一次唤醒所有订阅者.
+//! 不想 clone 值时，可以先 changed 等待新版本，再用 borrow 在读锁下直接访问它.
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+
+use crate::RecvError;
+
+/// 创建一个 watch channel，initial 是 Receiver 在第一次 send 之前读到的值.
+pub fn channel<T: Clone>(initial: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        value: RwLock::new(initial),
+        version: AtomicU64::new(0),
+        closed: AtomicBool::new(false),
+        lock: Mutex::new(()),
+        changed: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, seen: 0 },
+    )
+}
+
+struct Shared<T> {
+    value: RwLock<T>,
+    // version : send 的次数. 只在持有 value 的写锁时修改，所以持有读锁时读到的 version 与值一致.
+    version: AtomicU64,
+    // closed : Sender 是否已经被丢弃.
+    closed: AtomicBool,
+    // lock/changed : 等待新版本的 Receiver 在 changed 上等待. 通知方修改 version 之后先获取一次 lock，
+    // 保证等待方要么在检查时已经看到新版本，要么已经在 wait 中，不会错过 notify_all.
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn notify_all(&self) {
+        drop(self.lock.lock().unwrap());
+        self.changed.notify_all();
+    }
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 用 value 替换当前的值，并唤醒所有等待中的 Receiver. 旧值在这里被释放.
+    pub fn send(&self, value: T) {
+        let old = {
+            let mut current = self.shared.value.write().unwrap();
+            let old = std::mem::replace(&mut *current, value);
+            self.shared.version.fetch_add(1, Ordering::Release);
+            old
+        };
+        drop(old);
+        self.shared.notify_all();
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// 唤醒所有等待中的 Receiver，让它们的 recv 返回 Disconnected 而不是永远阻塞.
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.notify_all();
+    }
+}
+
+/// Receiver 可以被 clone，新的 Receiver 与原来的一样，已经看到了原来看到的版本.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // seen : 这个 Receiver 最后一次读取的值所对应的版本.
+    seen: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    /// 不等待，直接读取当前的值，并把它标记为已经看到.
+    pub fn get(&mut self) -> T {
+        let current = self.shared.value.read().unwrap();
+        self.seen = self.shared.version.load(Ordering::Acquire);
+        current.clone()
+    }
+
+    /// 阻塞直到有比上次读取更新的值，然后返回它. 在等待期间发生的多次 send 只会得到最后一次的值.
+    /// Sender 被丢弃之后，已经发送但还没有读取的值仍然会被返回一次，之后返回 Disconnected.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.changed()?;
+        Ok(self.get())
+    }
+}
+
+impl<T> Receiver<T> {
+    /// 是否有还没有读取过的新值.
+    pub fn has_changed(&self) -> bool {
+        self.shared.version.load(Ordering::Acquire) != self.seen
+    }
+
+    /// 不 clone，借出当前的值. 不会把它标记为已经看到.
+    /// 返回的引用持有读锁，在它被释放之前 send 会阻塞，所以不要长时间持有.
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.shared.value.read().unwrap()
+    }
+
+    /// 阻塞直到有比上次看到的更新的版本，并把它标记为已经看到，随后用 borrow 读取值.
+    /// 与 recv 一样，Sender 被丢弃之前的最后一次 send 仍然会被报告一次，之后返回 Disconnected，
+    /// 此时 borrow 仍然能读到最后的值.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut guard = self.shared.lock.lock().unwrap();
+        while !self.has_changed() {
+            if self.shared.closed.load(Ordering::Acquire) {
+                // closed 之前的最后一次 send 可能与上面的检查交错，再确认一次.
+                if self.has_changed() {
+                    break;
+                }
+                return Err(RecvError::Disconnected);
+            }
+            guard = self.shared.changed.wait(guard).unwrap();
+        }
+        drop(guard);
+        self.seen = self.shared.version.load(Ordering::Acquire);
+        Ok(())
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        Receiver {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn latest_value_wins() {
+        let (sender, mut receiver) = channel(0);
+        assert_eq!(receiver.get(), 0);
+        assert!(!receiver.has_changed());
+        sender.send(1);
+        sender.send(2);
+        assert!(receiver.has_changed());
+        assert_eq!(receiver.recv(), Ok(2));
+        assert!(!receiver.has_changed());
+    }
+
+    #[test]
+    fn wakes_all_receivers() {
+        let (sender, receiver) = channel(String::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let mut receiver = receiver.clone();
+                thread::spawn(move || receiver.recv())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(10));
+        sender.send(String::from("hello rustacean!"));
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), "hello rustacean!");
+        }
+    }
+
+    #[test]
+    fn sender_dropped() {
+        let (sender, mut receiver) = channel(0);
+        let mut other = receiver.clone();
+        let handle = thread::spawn(move || other.recv());
+        sender.send(1);
+        drop(sender);
+        // 丢弃之前发送的值仍然能读到一次.
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        assert_eq!(handle.join().unwrap(), Ok(1));
+    }
+
+    #[test]
+    fn burst_is_coalesced() {
+        let (sender, mut receiver) = channel(vec![0]);
+        let mut other = receiver.clone();
+        for i in 1..=100 {
+            sender.send(vec![i]);
+        }
+        // 每个 Receiver 各自记录看到的版本，100 次 send 只报告一次变化.
+        assert_eq!(receiver.changed(), Ok(()));
+        assert_eq!(*receiver.borrow(), [100]);
+        assert!(!receiver.has_changed());
+        assert!(other.has_changed());
+        assert_eq!(other.recv(), Ok(vec![100]));
+
+        drop(sender);
+        assert_eq!(receiver.changed(), Err(RecvError::Disconnected));
+        assert_eq!(*receiver.borrow(), [100]);
+    }
+}
@@ -0,0 +1,47 @@
+//! 用 futures 的 block_on 和 StreamExt 驱动 mpsc::Receiver 的 Stream 实现.
+
+#![cfg(feature = "futures")]
+
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::StreamExt;
+use my_channel::mpsc;
+
+#[test]
+fn collect_until_senders_dropped() {
+    let (sender, receiver) = mpsc::channel();
+    let handles: Vec<_> = (0..3)
+        .map(|t| {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for i in 0..50 {
+                    sender.send(t * 50 + i).unwrap();
+                    if i % 10 == 0 {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+    let mut received: Vec<i32> = block_on(receiver.collect());
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    received.sort();
+    assert_eq!(received, (0..150).collect::<Vec<_>>());
+}
+
+#[test]
+fn next_waits_for_message() {
+    let (sender, mut receiver) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(5));
+        sender.send("late").unwrap();
+    });
+    assert_eq!(block_on(receiver.next()), Some("late"));
+    handle.join().unwrap();
+    assert_eq!(block_on(receiver.next()), None);
+}
@@ -0,0 +1,100 @@
+//! 在 tokio 运行时中 .await One-Shot channel 的 Receiver. 发送方分别是另一个异步任务、
+//! 阻塞线程，以及没有发送就被丢弃的 Sender.
+
+#![cfg(all(feature = "async", not(loom)))]
+
+use std::time::Duration;
+
+use my_channel::{channel, RecvError};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn await_from_task() {
+    let (sender, receiver) = channel();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        sender.send(String::from("hello")).unwrap();
+    });
+    assert_eq!(receiver.await.as_deref(), Ok("hello"));
+}
+
+#[tokio::test]
+async fn await_from_blocking_thread() {
+    let (sender, receiver) = channel();
+    let handle = tokio::task::spawn_blocking(move || {
+        std::thread::sleep(Duration::from_millis(5));
+        sender.send(42).unwrap();
+    });
+    assert_eq!(receiver.await, Ok(42));
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn disconnected_and_timeout() {
+    let (sender, receiver) = channel::<i32>();
+    drop(sender);
+    assert_eq!(receiver.await, Err(RecvError::Disconnected));
+
+    // 超时丢弃 Future(即 Receiver)之后，send 把消息交还.
+    let (sender, receiver) = channel::<i32>();
+    assert!(tokio::time::timeout(Duration::from_millis(5), receiver)
+        .await
+        .is_err());
+    assert_eq!(sender.send(1).unwrap_err().into_inner(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn bounded_async_send_and_recv() {
+    let (mut sender, mut receiver) = my_channel::bounded::channel(2);
+    let producer = tokio::spawn(async move {
+        for i in 0..100 {
+            sender.send_async(i).await.unwrap();
+        }
+    });
+    let mut received = Vec::new();
+    while let Some(msg) = receiver.recv_async().await {
+        received.push(msg);
+    }
+    producer.await.unwrap();
+    assert_eq!(received, (0..100).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn bounded_async_sender_with_blocking_receiver() {
+    let (mut sender, mut receiver) = my_channel::bounded::channel(1);
+    // 阻塞的接收方在线程上停放，异步的发送方登记 Waker，两边共用同一条唤醒路径.
+    let consumer = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        while let Ok(msg) = receiver.recv() {
+            received.push(msg);
+        }
+        received
+    });
+    for i in 0..20 {
+        sender.send_async(i).await.unwrap();
+    }
+    drop(sender);
+    assert_eq!(consumer.join().unwrap(), (0..20).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn bounded_send_async_cancelled_and_closed() {
+    let (mut sender, mut receiver) = my_channel::bounded::channel(1);
+    sender.send_async(1).await.unwrap();
+    // 队列已满，等待中被取消的 send_async 不会占用槽位.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(5), sender.send_async(2))
+            .await
+            .is_err()
+    );
+    assert_eq!(receiver.recv_async().await, Some(1));
+    assert_eq!(receiver.try_recv(), Err(my_channel::TryRecvError::Empty));
+    sender.send_async(3).await.unwrap();
+    assert_eq!(receiver.recv_async().await, Some(3));
+
+    // Receiver 被丢弃之后，等待中的 send_async 被唤醒并交还消息.
+    sender.send_async(4).await.unwrap();
+    let handle = tokio::spawn(async move { sender.send_async(5).await });
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    drop(receiver);
+    assert_eq!(handle.await.unwrap().unwrap_err().into_inner(), 5);
+}